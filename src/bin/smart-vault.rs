@@ -0,0 +1,627 @@
+use std::{error::Error, fmt::Debug, fs, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use smart_vault::{
+    client::{ed25519_verify_instruction, SubscriptionRef, VaultAccount, VaultClient},
+    state::{
+        AppSchedule, AuctionMode, ConsensusAction, ProtocolConfig, ProtocolParams,
+        VaultAppCounterState, VaultAppState, VaultBidCommitState, VaultBidderState,
+        VaultMeasurementRegistryState, VaultMetaDataState, VaultOperatorState,
+        VaultReputationState, VaultSubBidsState, VaultSubEscrowState, VaultTreasuryState,
+        VaultUserState, VaultUserSubscriptionState,
+    },
+};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    name = "smart-vault",
+    about = "Operator tool for the smart vault program"
+)]
+struct Cli {
+    #[arg(long, default_value = "http://localhost:8899")]
+    url: String,
+
+    /// Fee payer and signer of every instruction.
+    #[arg(long, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+
+    #[arg(long)]
+    program_id: Pubkey,
+
+    /// Token account of the treasury PDA, needed by instructions that move tokens.
+    #[arg(long)]
+    treasury_ata: Option<Pubkey>,
+
+    /// Write the signed transaction to this file instead of sending it. Nothing is read from the
+    /// cluster then, values it would provide have to be passed as flags.
+    #[arg(long, requires = "blockhash")]
+    output: Option<PathBuf>,
+
+    /// Blockhash to sign with, so transactions can be built without reaching the cluster.
+    #[arg(long)]
+    blockhash: Option<Hash>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Init {
+        #[arg(long, value_delimiter = ',', required = true)]
+        signers: Vec<Pubkey>,
+        #[arg(long)]
+        threshold: u8,
+        /// Hex encoded enclave measurement of the consensus build.
+        #[arg(long, value_parser = parse_measurement)]
+        measurement: [u8; 32],
+        #[arg(long)]
+        attestation_proof: String,
+        #[command(flatten)]
+        params: ParamsArgs,
+    },
+    AddApp {
+        /// Id the app is registered under, read from the app counter by default.
+        #[arg(long)]
+        app_id: Option<u64>,
+        #[arg(long)]
+        creator_ata: Pubkey,
+        #[arg(long)]
+        rent: u64,
+        #[arg(long)]
+        ipfs_hash: String,
+        #[command(flatten)]
+        schedule: ScheduleArgs,
+    },
+    Topup {
+        #[arg(long)]
+        user_ata: Pubkey,
+        #[arg(long)]
+        amount: u64,
+    },
+    StartSub {
+        /// Id of the new subscription, read from the subscriber's state by default.
+        #[arg(long)]
+        sub_id: Option<u64>,
+        #[arg(long)]
+        app_id: u64,
+        #[arg(long)]
+        max_rent: u64,
+        #[arg(long)]
+        params_hash: String,
+        #[arg(long, default_value_t = 0)]
+        escrow_amount: u64,
+        /// Run a reverse Dutch auction starting at this rent instead of a sealed bid auction.
+        #[arg(long)]
+        dutch_start_rent: Option<u64>,
+    },
+    CloseSub {
+        #[arg(long)]
+        sub_id: u64,
+    },
+    Bid {
+        #[command(flatten)]
+        sub: SubArgs,
+        #[arg(long)]
+        amount: u64,
+        /// Hex encoded salt used for the commitment.
+        #[arg(long, value_parser = parse_measurement)]
+        salt: [u8; 32],
+        #[command(flatten)]
+        consensus: ConsensusArgs,
+    },
+    Claimbid {
+        #[command(flatten)]
+        sub: SubArgs,
+        /// App the subscription runs, read from the subscription by default.
+        #[arg(long)]
+        app_id: Option<u64>,
+        /// Bidders ranked ahead of the payer, best first. Read from the bid list by default, pass
+        /// the flag without values when the payer won outright.
+        #[arg(long, value_delimiter = ',', num_args = 0..)]
+        outranked_by: Option<Vec<Pubkey>>,
+        #[command(flatten)]
+        consensus: ConsensusArgs,
+    },
+    ReportWork {
+        #[command(flatten)]
+        sub: SubArgs,
+        /// App the subscription runs, read from the subscription by default.
+        #[arg(long)]
+        app_id: Option<u64>,
+        #[arg(long)]
+        nonce: u64,
+        #[arg(long)]
+        bid_winner_ata: Pubkey,
+        #[arg(long)]
+        creator_ata: Pubkey,
+        #[command(flatten)]
+        consensus: ConsensusArgs,
+    },
+    /// Print the decoded contents of a vault account.
+    Show {
+        #[arg(value_enum)]
+        kind: AccountKind,
+        address: Pubkey,
+    },
+}
+
+#[derive(Args)]
+struct SubArgs {
+    /// Owner of the subscription.
+    #[arg(long)]
+    user: Pubkey,
+    #[arg(long)]
+    sub_id: u64,
+}
+
+#[derive(Args)]
+struct ConsensusArgs {
+    /// Consensus signer keypair files, one per approval.
+    #[arg(long = "consensus-keypair", required = true)]
+    consensus_keypairs: Vec<String>,
+    /// Slot after which the approval is rejected, defaults to 150 slots from now.
+    #[arg(long)]
+    expiry_slot: Option<u64>,
+    /// Bidder state nonce of the payer, read from the cluster by default.
+    #[arg(long)]
+    operator_nonce: Option<u64>,
+}
+
+#[derive(Args)]
+struct ParamsArgs {
+    #[arg(long, default_value_t = 60)]
+    bid_window: u64,
+    #[arg(long, default_value_t = 60)]
+    claim_window: u64,
+    #[arg(long, default_value_t = 3600)]
+    report_interval: u64,
+    #[arg(long, default_value_t = 600)]
+    report_grace: u64,
+    #[arg(long, default_value_t = 1)]
+    min_topup: u64,
+    #[arg(long, default_value_t = 1024)]
+    max_attestation_len: u32,
+    #[arg(long, default_value_t = 64)]
+    max_ipfs_hash_len: u32,
+    #[arg(long, default_value_t = 64)]
+    max_params_hash_len: u32,
+    #[arg(long, default_value_t = 1000)]
+    slash_bps: u16,
+    #[arg(long, default_value_t = 2000)]
+    reporter_share_bps: u16,
+    #[arg(long, default_value_t = 3000)]
+    reputation_weight_bps: u16,
+    #[arg(long, default_value_t = 0)]
+    keeper_bounty: u64,
+    #[arg(long, default_value_t = 0)]
+    protocol_fee_bps: u16,
+    #[arg(long, default_value_t = 2)]
+    key_rotation_grace: u64,
+    #[arg(long, default_value_t = 604_800)]
+    attestation_ttl: u64,
+    #[arg(long, default_value_t = 20)]
+    reveal_window: u64,
+    #[arg(long, default_value_t = 0)]
+    bid_bond: u64,
+    /// Upper bound for every window of an app schedule.
+    #[arg(long, default_value_t = 2_592_000)]
+    max_schedule_window: u64,
+}
+
+/// Zero values fall back to the protocol defaults.
+#[derive(Args)]
+struct ScheduleArgs {
+    #[arg(long, default_value_t = 0)]
+    report_interval: u64,
+    #[arg(long, default_value_t = 0)]
+    grace_period: u64,
+    #[arg(long, default_value_t = 0)]
+    bid_window: u64,
+    #[arg(long, default_value_t = 0)]
+    claim_window: u64,
+    #[arg(long, default_value_t = 0)]
+    reveal_window: u64,
+}
+
+#[derive(Clone, ValueEnum)]
+enum AccountKind {
+    Metadata,
+    Config,
+    Registry,
+    Treasury,
+    AppCounter,
+    App,
+    User,
+    Sub,
+    SubBids,
+    BidCommit,
+    Escrow,
+    Operator,
+    Reputation,
+    Bidder,
+}
+
+impl From<ParamsArgs> for ProtocolParams {
+    fn from(args: ParamsArgs) -> Self {
+        ProtocolParams {
+            bid_window: args.bid_window,
+            claim_window: args.claim_window,
+            report_interval: args.report_interval,
+            report_grace: args.report_grace,
+            min_topup: args.min_topup,
+            max_attestation_len: args.max_attestation_len,
+            max_ipfs_hash_len: args.max_ipfs_hash_len,
+            max_params_hash_len: args.max_params_hash_len,
+            slash_bps: args.slash_bps,
+            reporter_share_bps: args.reporter_share_bps,
+            reputation_weight_bps: args.reputation_weight_bps,
+            keeper_bounty: args.keeper_bounty,
+            protocol_fee_bps: args.protocol_fee_bps,
+            key_rotation_grace: args.key_rotation_grace,
+            attestation_ttl: args.attestation_ttl,
+            reveal_window: args.reveal_window,
+            bid_bond: args.bid_bond,
+            max_schedule_window: args.max_schedule_window,
+        }
+    }
+}
+
+impl From<ScheduleArgs> for AppSchedule {
+    fn from(args: ScheduleArgs) -> Self {
+        AppSchedule {
+            report_interval: args.report_interval,
+            grace_period: args.grace_period,
+            bid_window: args.bid_window,
+            claim_window: args.claim_window,
+            reveal_window: args.reveal_window,
+        }
+    }
+}
+
+fn parse_measurement(value: &str) -> Result<[u8; 32], String> {
+    let value = value.trim_start_matches("0x");
+    if value.len() != 64 || !value.is_ascii() {
+        return Err("expected 32 hex encoded bytes".to_string());
+    }
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16).map_err(|err| err.to_string())?;
+    }
+    Ok(bytes)
+}
+
+fn expand_tilde(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+fn read_keypair(path: &str) -> CliResult<Keypair> {
+    read_keypair_file(expand_tilde(path)).map_err(|err| format!("{path}: {err}").into())
+}
+
+struct Context {
+    rpc: RpcClient,
+    payer: Keypair,
+    vault: VaultClient,
+    treasury_ata: Option<Pubkey>,
+    output: Option<PathBuf>,
+    blockhash: Option<Hash>,
+}
+
+impl Context {
+    fn treasury_ata(&self) -> CliResult<Pubkey> {
+        self.treasury_ata
+            .ok_or_else(|| "--treasury-ata is required for this command".into())
+    }
+
+    /// With `--output` nothing is fetched, so the value has to come from `flag`.
+    fn given_or_fetch<T>(
+        &self,
+        given: Option<T>,
+        flag: &str,
+        fetch: impl FnOnce() -> CliResult<T>,
+    ) -> CliResult<T> {
+        match given {
+            Some(value) => Ok(value),
+            None if self.output.is_some() => {
+                Err(format!("{flag} is required with --output").into())
+            }
+            None => fetch(),
+        }
+    }
+
+    fn account<T: VaultAccount>(&self, address: &Pubkey) -> CliResult<T> {
+        let data = self.rpc.get_account_data(address)?;
+        Ok(T::unpack_account(&data)?)
+    }
+
+    // the payer signs every instruction, so it is the executor the subscription expects
+    fn subscription(&self, sub: &SubArgs, app_id: Option<u64>) -> CliResult<SubscriptionRef> {
+        let app_id = self.given_or_fetch(app_id, "--app-id", || {
+            let address = self
+                .vault
+                .find_subscription_address(&sub.user, sub.sub_id)
+                .0;
+            let state: VaultUserSubscriptionState = self.account(&address)?;
+            Ok(state.app_id)
+        })?;
+
+        Ok(SubscriptionRef {
+            user: sub.user,
+            id: sub.sub_id,
+            app_id,
+            executor: self.payer.pubkey(),
+        })
+    }
+
+    // first use of the bidder state creates it, so a missing account means nonce zero
+    fn operator_nonce(&self, operator: &Pubkey) -> CliResult<u64> {
+        let address = self.vault.find_bidder_address(operator).0;
+        let account = self
+            .rpc
+            .get_account_with_commitment(&address, CommitmentConfig::confirmed())?
+            .value;
+        match account {
+            Some(account) => Ok(VaultBidderState::unpack_account(&account.data)?.nonce),
+            None => Ok(0),
+        }
+    }
+
+    /// Signs the consensus message with every given keypair, returns the verify instruction and
+    /// the expiry slot the approved instruction has to carry.
+    fn consensus_approval(
+        &self,
+        consensus: &ConsensusArgs,
+        action: ConsensusAction,
+        subscription: &Pubkey,
+        value: u64,
+    ) -> CliResult<(Instruction, u64)> {
+        let operator = self.payer.pubkey();
+        let expiry_slot = self.given_or_fetch(consensus.expiry_slot, "--expiry-slot", || {
+            Ok(self.rpc.get_slot()? + 150)
+        })?;
+        let operator_nonce =
+            self.given_or_fetch(consensus.operator_nonce, "--operator-nonce", || {
+                self.operator_nonce(&operator)
+            })?;
+
+        let message = self.vault.consensus_message(
+            action,
+            subscription,
+            &operator,
+            operator_nonce,
+            value,
+            expiry_slot,
+        );
+        let message = borsh::to_vec(&message)?;
+
+        let approvals = consensus
+            .consensus_keypairs
+            .iter()
+            .map(|path| {
+                let signer = read_keypair(path)?;
+                let signature: [u8; 64] = signer.sign_message(&message).into();
+                Ok((signer.pubkey(), signature))
+            })
+            .collect::<CliResult<Vec<_>>>()?;
+
+        Ok((
+            ed25519_verify_instruction(&approvals, &message),
+            expiry_slot,
+        ))
+    }
+
+    fn submit(&self, instructions: &[Instruction]) -> CliResult<()> {
+        let blockhash = match self.blockhash {
+            Some(blockhash) => blockhash,
+            None => self.rpc.get_latest_blockhash()?,
+        };
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+
+        match &self.output {
+            Some(path) => {
+                fs::write(path, bincode::serialize(&transaction)?)?;
+                println!("Transaction written to {}", path.display());
+            }
+            None => {
+                let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+                println!("Signature: {signature}");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn show<T: VaultAccount + Debug>(ctx: &Context, address: &Pubkey) -> CliResult<()> {
+    let state: T = ctx.account(address)?;
+    println!("{state:#?}");
+    Ok(())
+}
+
+fn main() -> CliResult<()> {
+    let cli = Cli::parse();
+
+    let ctx = Context {
+        rpc: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        payer: read_keypair(&cli.keypair)?,
+        vault: VaultClient::new(cli.program_id, cli.treasury_ata.unwrap_or_default()),
+        treasury_ata: cli.treasury_ata,
+        output: cli.output,
+        blockhash: cli.blockhash,
+    };
+    let payer = ctx.payer.pubkey();
+
+    match cli.command {
+        Command::Init {
+            signers,
+            threshold,
+            measurement,
+            attestation_proof,
+            params,
+        } => ctx.submit(&[ctx.vault.init(
+            &payer,
+            signers,
+            threshold,
+            measurement,
+            attestation_proof,
+            params.into(),
+        )]),
+        Command::AddApp {
+            app_id,
+            creator_ata,
+            rent,
+            ipfs_hash,
+            schedule,
+        } => {
+            let app_id = ctx.given_or_fetch(app_id, "--app-id", || {
+                let app_counter: VaultAppCounterState =
+                    ctx.account(&ctx.vault.find_app_counter_address().0)?;
+                Ok(app_counter.counter)
+            })?;
+            println!("App id: {app_id}");
+            ctx.submit(&[ctx.vault.add_app(
+                &payer,
+                &creator_ata,
+                app_id,
+                rent,
+                ipfs_hash,
+                schedule.into(),
+            )])
+        }
+        Command::Topup { user_ata, amount } => {
+            ctx.treasury_ata()?;
+            ctx.submit(&[ctx.vault.topup(&payer, &user_ata, amount)])
+        }
+        Command::StartSub {
+            sub_id,
+            app_id,
+            max_rent,
+            params_hash,
+            escrow_amount,
+            dutch_start_rent,
+        } => {
+            let sub_id = ctx.given_or_fetch(sub_id, "--sub-id", || {
+                let user_state: VaultUserState =
+                    ctx.account(&ctx.vault.find_user_address(&payer).0)?;
+                Ok(user_state.count)
+            })?;
+            let auction_mode = match dutch_start_rent {
+                Some(start_rent) => AuctionMode::ReverseDutch { start_rent },
+                None => AuctionMode::SealedBid,
+            };
+            println!("Subscription id: {sub_id}");
+            ctx.submit(&[ctx
+                .vault
+                .start_subscription(&payer, sub_id, app_id, max_rent, params_hash, escrow_amount)
+                .auction_mode(auction_mode)
+                .build()])
+        }
+        Command::CloseSub { sub_id } => ctx.submit(&[ctx.vault.close_sub(&payer, sub_id)]),
+        Command::Bid {
+            sub,
+            amount,
+            salt,
+            consensus,
+        } => {
+            let subscription = ctx.vault.find_subscription_address(&sub.user, sub.sub_id).0;
+            let (approval, expiry_slot) =
+                ctx.consensus_approval(&consensus, ConsensusAction::Bid, &subscription, amount)?;
+            ctx.submit(&[
+                approval,
+                ctx.vault
+                    .bid(&payer, &subscription, amount, salt, expiry_slot),
+            ])
+        }
+        Command::Claimbid {
+            sub,
+            app_id,
+            outranked_by,
+            consensus,
+        } => {
+            let subscription = ctx.vault.find_subscription_address(&sub.user, sub.sub_id).0;
+            let sub_ref = ctx.subscription(&sub, app_id)?;
+            let outranked_by = ctx.given_or_fetch(outranked_by, "--outranked-by", || {
+                let sub_bids: VaultSubBidsState =
+                    ctx.account(&ctx.vault.find_sub_bids_address(&subscription).0)?;
+                Ok(sub_bids
+                    .bids
+                    .iter()
+                    .map(|ranked_bid| ranked_bid.bidder)
+                    .take_while(|bidder| *bidder != payer)
+                    .collect())
+            })?;
+            let (approval, expiry_slot) =
+                ctx.consensus_approval(&consensus, ConsensusAction::ClaimBid, &subscription, 0)?;
+            ctx.submit(&[
+                approval,
+                ctx.vault
+                    .claimbid_with(&payer, &sub_ref, &outranked_by, expiry_slot),
+            ])
+        }
+        Command::ReportWork {
+            sub,
+            app_id,
+            nonce,
+            bid_winner_ata,
+            creator_ata,
+            consensus,
+        } => {
+            ctx.treasury_ata()?;
+            let subscription = ctx.vault.find_subscription_address(&sub.user, sub.sub_id).0;
+            let sub_ref = ctx.subscription(&sub, app_id)?;
+            let (approval, expiry_slot) = ctx.consensus_approval(
+                &consensus,
+                ConsensusAction::ReportWork,
+                &subscription,
+                nonce,
+            )?;
+            ctx.submit(&[
+                approval,
+                ctx.vault.report_work_with(
+                    &bid_winner_ata,
+                    &sub_ref,
+                    &creator_ata,
+                    nonce,
+                    expiry_slot,
+                ),
+            ])
+        }
+        Command::Show { kind, address } => match kind {
+            AccountKind::Metadata => show::<VaultMetaDataState>(&ctx, &address),
+            AccountKind::Config => show::<ProtocolConfig>(&ctx, &address),
+            AccountKind::Registry => show::<VaultMeasurementRegistryState>(&ctx, &address),
+            AccountKind::Treasury => show::<VaultTreasuryState>(&ctx, &address),
+            AccountKind::AppCounter => show::<VaultAppCounterState>(&ctx, &address),
+            AccountKind::App => show::<VaultAppState>(&ctx, &address),
+            AccountKind::User => show::<VaultUserState>(&ctx, &address),
+            AccountKind::Sub => show::<VaultUserSubscriptionState>(&ctx, &address),
+            AccountKind::SubBids => show::<VaultSubBidsState>(&ctx, &address),
+            AccountKind::BidCommit => show::<VaultBidCommitState>(&ctx, &address),
+            AccountKind::Escrow => show::<VaultSubEscrowState>(&ctx, &address),
+            AccountKind::Operator => show::<VaultOperatorState>(&ctx, &address),
+            AccountKind::Reputation => show::<VaultReputationState>(&ctx, &address),
+            AccountKind::Bidder => show::<VaultBidderState>(&ctx, &address),
+        },
+    }
+}
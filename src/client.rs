@@ -0,0 +1,871 @@
+use borsh::BorshDeserialize;
+use solana_program::{
+    borsh1::try_from_slice_unchecked,
+    ed25519_program,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::IsInitialized,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::instructions,
+};
+
+use crate::{
+    instruction::SmartVaultInstrunction,
+    processor::{
+        APP_COUNTER, APP_STATE, BIDDER_STATE, BID_COMMIT, MEASUREMENT_REGISTRY, OPERATOR_STATE,
+        PROTOCOL_CONFIG, REPUTATION_STATE, SUB_BIDS, SUB_ESCROW, SUB_STATE, TREASURY_STATE,
+        USER_STATE, VAULT_METADATA,
+    },
+    state::{
+        AppSchedule, AuctionMode, ConsensusAction, ConsensusMessage, ProtocolConfig,
+        ProtocolParams, VaultAppCounterState, VaultAppState, VaultBidCommitState, VaultBidderState,
+        VaultMeasurementRegistryState, VaultMetaDataState, VaultOperatorState,
+        VaultReputationState, VaultSubBidsState, VaultSubEscrowState, VaultTreasuryState,
+        VaultUserState, VaultUserSubscriptionState,
+    },
+    utils::CONSENSUS_MSG_VERSION,
+};
+
+pub use crate::processor::{attestation_commitment, bid_commitment};
+
+/// Ed25519 precompile instruction carrying consensus approvals of one message. It has to be
+/// placed in the same transaction as the instruction it approves.
+pub fn ed25519_verify_instruction(approvals: &[(Pubkey, [u8; 64])], message: &[u8]) -> Instruction {
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+
+    let data_start = OFFSETS_START + approvals.len() * OFFSETS_SIZE;
+    let message_offset = data_start + approvals.len() * (32 + 64);
+
+    let mut data = vec![approvals.len() as u8, 0];
+    for i in 0..approvals.len() {
+        let public_key_offset = data_start + i * (32 + 64);
+        let signature_offset = public_key_offset + 32;
+        for value in [
+            signature_offset as u16,
+            u16::MAX,
+            public_key_offset as u16,
+            u16::MAX,
+            message_offset as u16,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    for (signer, signature) in approvals {
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(signature);
+    }
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Decodes program accounts. Accounts are allocated with room to grow, so trailing bytes are ignored.
+pub trait VaultAccount: BorshDeserialize + IsInitialized {
+    fn unpack_account(data: &[u8]) -> Result<Self, ProgramError> {
+        let state =
+            try_from_slice_unchecked::<Self>(data).map_err(|_| ProgramError::InvalidAccountData)?;
+
+        if !state.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        Ok(state)
+    }
+}
+
+impl VaultAccount for VaultMetaDataState {}
+impl VaultAccount for ProtocolConfig {}
+impl VaultAccount for VaultMeasurementRegistryState {}
+impl VaultAccount for VaultTreasuryState {}
+impl VaultAccount for VaultAppCounterState {}
+impl VaultAccount for VaultAppState {}
+impl VaultAccount for VaultUserState {}
+impl VaultAccount for VaultUserSubscriptionState {}
+impl VaultAccount for VaultSubBidsState {}
+impl VaultAccount for VaultBidCommitState {}
+impl VaultAccount for VaultSubEscrowState {}
+impl VaultAccount for VaultOperatorState {}
+impl VaultAccount for VaultReputationState {}
+impl VaultAccount for VaultBidderState {}
+
+/// Subscription fields the instruction builders need, for callers that know them without
+/// fetching the account.
+#[derive(Clone, Copy, Debug)]
+pub struct SubscriptionRef {
+    pub user: Pubkey,
+    pub id: u64,
+    pub app_id: u64,
+    pub executor: Pubkey,
+}
+
+impl SubscriptionRef {
+    pub fn new(user: &Pubkey, sub: &VaultUserSubscriptionState) -> Self {
+        Self {
+            user: *user,
+            id: sub.id,
+            app_id: sub.app_id,
+            executor: sub.executor,
+        }
+    }
+}
+
+/// Builds instructions for one deployment of the program. `treasury_ata` is the token account
+/// of the treasury PDA that holds every deposit, stake and bond.
+pub struct VaultClient {
+    pub program_id: Pubkey,
+    pub treasury_ata: Pubkey,
+}
+
+impl VaultClient {
+    pub fn new(program_id: Pubkey, treasury_ata: Pubkey) -> Self {
+        Self {
+            program_id,
+            treasury_ata,
+        }
+    }
+
+    pub fn find_metadata_address(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VAULT_METADATA.as_bytes()], &self.program_id)
+    }
+
+    pub fn find_app_counter_address(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[APP_COUNTER.as_bytes()], &self.program_id)
+    }
+
+    pub fn find_treasury_address(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[TREASURY_STATE.as_bytes()], &self.program_id)
+    }
+
+    pub fn find_config_address(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PROTOCOL_CONFIG.as_bytes()], &self.program_id)
+    }
+
+    pub fn find_measurement_registry_address(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MEASUREMENT_REGISTRY.as_bytes()], &self.program_id)
+    }
+
+    pub fn find_app_address(&self, app_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[APP_STATE.as_bytes(), app_id.to_be_bytes().as_ref()],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_user_address(&self, user: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.as_ref()], &self.program_id)
+    }
+
+    pub fn find_subscription_address(&self, user: &Pubkey, sub_id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                SUB_STATE.as_bytes(),
+                user.as_ref(),
+                sub_id.to_be_bytes().as_ref(),
+            ],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_escrow_address(&self, subscription: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[SUB_ESCROW.as_bytes(), subscription.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_bidder_address(&self, bidder: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[BIDDER_STATE.as_bytes(), bidder.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_operator_address(&self, operator: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[OPERATOR_STATE.as_bytes(), operator.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_reputation_address(&self, operator: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[REPUTATION_STATE.as_bytes(), operator.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_bid_commit_address(&self, subscription: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                BID_COMMIT.as_bytes(),
+                subscription.as_ref(),
+                bidder.as_ref(),
+            ],
+            &self.program_id,
+        )
+    }
+
+    pub fn find_sub_bids_address(&self, subscription: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[SUB_BIDS.as_bytes(), subscription.as_ref()],
+            &self.program_id,
+        )
+    }
+
+    /// `operator_nonce` is the operator's current bidder state nonce.
+    pub fn consensus_message(
+        &self,
+        action: ConsensusAction,
+        subscription: &Pubkey,
+        operator: &Pubkey,
+        operator_nonce: u64,
+        value: u64,
+        expiry_slot: u64,
+    ) -> ConsensusMessage {
+        ConsensusMessage {
+            version: CONSENSUS_MSG_VERSION,
+            action,
+            program_id: self.program_id,
+            subscription: *subscription,
+            operator: *operator,
+            operator_nonce,
+            value,
+            expiry_slot,
+        }
+    }
+
+    pub fn init(
+        &self,
+        initializer: &Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        measurement: [u8; 32],
+        attestation_proof: String,
+        params: ProtocolParams,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::Init {
+                signers,
+                threshold,
+                measurement,
+                attestation_proof,
+                params,
+            },
+            vec![
+                AccountMeta::new(*initializer, true),
+                AccountMeta::new(self.find_metadata_address().0, false),
+                AccountMeta::new(self.find_app_counter_address().0, false),
+                AccountMeta::new(self.find_treasury_address().0, false),
+                AccountMeta::new(self.find_config_address().0, false),
+                AccountMeta::new(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    pub fn join(
+        &self,
+        operator: &Pubkey,
+        operator_ata: &Pubkey,
+        attestation_proof: String,
+        measurement: [u8; 32],
+        transit_key: &Pubkey,
+        stake_amount: u64,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::Join {
+                attestation_proof,
+                measurement,
+                transit_key: *transit_key,
+                stake_amount,
+            },
+            vec![
+                AccountMeta::new(*operator, true),
+                AccountMeta::new(*operator_ata, false),
+                AccountMeta::new(self.find_operator_address(operator).0, false),
+                AccountMeta::new(self.find_reputation_address(operator).0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    /// `app_id` is the current value of the app counter.
+    pub fn add_app(
+        &self,
+        creator: &Pubkey,
+        creator_ata: &Pubkey,
+        app_id: u64,
+        rent_amount: u64,
+        ipfs_hash: String,
+        schedule: AppSchedule,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::AddApp {
+                rent_amount,
+                ipfs_hash,
+                schedule,
+            },
+            vec![
+                AccountMeta::new(*creator, true),
+                AccountMeta::new_readonly(*creator_ata, false),
+                AccountMeta::new(self.find_app_counter_address().0, false),
+                AccountMeta::new(self.find_app_address(app_id).0, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    pub fn topup(&self, user: &Pubkey, user_ata: &Pubkey, amount: u64) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::TopUp { amount },
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new(*user_ata, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    /// `sub_id` is the subscriber's current subscription count.
+    pub fn start_subscription(
+        &self,
+        subscriber: &Pubkey,
+        sub_id: u64,
+        app_id: u64,
+        max_rent: u64,
+        params_hash: String,
+        escrow_amount: u64,
+    ) -> StartSubscriptionBuilder<'_> {
+        StartSubscriptionBuilder {
+            client: self,
+            subscriber: *subscriber,
+            sub_id,
+            app_id,
+            max_rent,
+            params_hash,
+            escrow_amount,
+            auction_mode: AuctionMode::SealedBid,
+        }
+    }
+
+    /// Reveals a committed bid, or takes the current offer of a reverse Dutch auction.
+    pub fn bid(
+        &self,
+        bidder: &Pubkey,
+        subscription: &Pubkey,
+        bid_amount: u64,
+        salt: [u8; 32],
+        expiry_slot: u64,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::Bid {
+                bid_amount,
+                salt,
+                expiry_slot,
+            },
+            vec![
+                AccountMeta::new(*bidder, true),
+                AccountMeta::new(self.find_bidder_address(bidder).0, false),
+                AccountMeta::new_readonly(self.find_operator_address(bidder).0, false),
+                AccountMeta::new_readonly(self.find_reputation_address(bidder).0, false),
+                AccountMeta::new(self.find_bid_commit_address(subscription, bidder).0, false),
+                AccountMeta::new(self.find_sub_bids_address(subscription).0, false),
+                AccountMeta::new(*subscription, false),
+                AccountMeta::new_readonly(self.find_metadata_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(instructions::ID, false),
+            ],
+        )
+    }
+
+    /// Bidders ranked above the claimer are read from `sub_bids` so their commits can be forfeited.
+    pub fn claimbid(
+        &self,
+        bid_winner: &Pubkey,
+        user: &Pubkey,
+        sub: &VaultUserSubscriptionState,
+        sub_bids: &VaultSubBidsState,
+        expiry_slot: u64,
+    ) -> Instruction {
+        let outranked_by: Vec<Pubkey> = sub_bids
+            .bids
+            .iter()
+            .map(|ranked_bid| ranked_bid.bidder)
+            .take_while(|bidder| bidder != bid_winner)
+            .collect();
+
+        self.claimbid_with(
+            bid_winner,
+            &SubscriptionRef::new(user, sub),
+            &outranked_by,
+            expiry_slot,
+        )
+    }
+
+    /// `outranked_by` are the bidders ranked ahead of `bid_winner`, best first.
+    pub fn claimbid_with(
+        &self,
+        bid_winner: &Pubkey,
+        sub: &SubscriptionRef,
+        outranked_by: &[Pubkey],
+        expiry_slot: u64,
+    ) -> Instruction {
+        let user = &sub.user;
+        let subscription = self.find_subscription_address(user, sub.id).0;
+
+        let mut accounts = vec![
+            AccountMeta::new(*bid_winner, true),
+            AccountMeta::new(self.find_bidder_address(bid_winner).0, false),
+            AccountMeta::new_readonly(self.find_operator_address(bid_winner).0, false),
+            AccountMeta::new(subscription, false),
+            AccountMeta::new_readonly(self.find_app_address(sub.app_id).0, false),
+            AccountMeta::new_readonly(self.find_metadata_address().0, false),
+            AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+            AccountMeta::new_readonly(instructions::ID, false),
+            AccountMeta::new_readonly(self.find_sub_bids_address(&subscription).0, false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new(self.find_user_address(user).0, false),
+        ];
+        accounts.extend(outranked_by.iter().flat_map(|bidder| {
+            [
+                AccountMeta::new(self.find_bid_commit_address(&subscription, bidder).0, false),
+                AccountMeta::new(self.find_reputation_address(bidder).0, false),
+            ]
+        }));
+
+        self.instruction(SmartVaultInstrunction::ClaimBid { expiry_slot }, accounts)
+    }
+
+    pub fn report_work(
+        &self,
+        bid_winner_ata: &Pubkey,
+        user: &Pubkey,
+        sub: &VaultUserSubscriptionState,
+        creator_ata: &Pubkey,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Instruction {
+        self.report_work_with(
+            bid_winner_ata,
+            &SubscriptionRef::new(user, sub),
+            creator_ata,
+            nonce,
+            expiry_slot,
+        )
+    }
+
+    pub fn report_work_with(
+        &self,
+        bid_winner_ata: &Pubkey,
+        sub: &SubscriptionRef,
+        creator_ata: &Pubkey,
+        nonce: u64,
+        expiry_slot: u64,
+    ) -> Instruction {
+        let bid_winner = &sub.executor;
+        let user = &sub.user;
+        let subscription = self.find_subscription_address(user, sub.id).0;
+
+        self.instruction(
+            SmartVaultInstrunction::ReportWork { nonce, expiry_slot },
+            vec![
+                AccountMeta::new_readonly(*bid_winner, true),
+                AccountMeta::new(self.find_bidder_address(bid_winner).0, false),
+                AccountMeta::new(self.find_operator_address(bid_winner).0, false),
+                AccountMeta::new(self.find_reputation_address(bid_winner).0, false),
+                AccountMeta::new(*bid_winner_ata, false),
+                AccountMeta::new(subscription, false),
+                AccountMeta::new(self.find_escrow_address(&subscription).0, false),
+                AccountMeta::new_readonly(self.find_app_address(sub.app_id).0, false),
+                AccountMeta::new(*creator_ata, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_metadata_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(instructions::ID, false),
+            ],
+        )
+    }
+
+    pub fn close_sub(&self, user: &Pubkey, sub_id: u64) -> Instruction {
+        let subscription = self.find_subscription_address(user, sub_id).0;
+
+        self.instruction(
+            SmartVaultInstrunction::CloseSub {},
+            vec![
+                AccountMeta::new_readonly(*user, true),
+                AccountMeta::new(subscription, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new(self.find_escrow_address(&subscription).0, false),
+            ],
+        )
+    }
+
+    pub fn report_fault(
+        &self,
+        keeper: &Pubkey,
+        keeper_ata: &Pubkey,
+        user: &Pubkey,
+        sub: &VaultUserSubscriptionState,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::ReportFault {},
+            vec![
+                AccountMeta::new_readonly(*keeper, true),
+                AccountMeta::new(*keeper_ata, false),
+                AccountMeta::new(self.find_subscription_address(user, sub.id).0, false),
+                AccountMeta::new_readonly(self.find_app_address(sub.app_id).0, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new(self.find_operator_address(&sub.executor).0, false),
+                AccountMeta::new(self.find_reputation_address(&sub.executor).0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+        )
+    }
+
+    /// `sub_bids` is needed when the auction failed because every ranked bidder missed its claim.
+    pub fn reassign_sub(
+        &self,
+        keeper: &Pubkey,
+        keeper_ata: &Pubkey,
+        user: &Pubkey,
+        sub: &VaultUserSubscriptionState,
+        sub_bids: Option<&VaultSubBidsState>,
+    ) -> Instruction {
+        let subscription = self.find_subscription_address(user, sub.id).0;
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*keeper, true),
+            AccountMeta::new(*keeper_ata, false),
+            AccountMeta::new(subscription, false),
+            AccountMeta::new_readonly(self.find_app_address(sub.app_id).0, false),
+            AccountMeta::new(self.find_operator_address(&sub.executor).0, false),
+            AccountMeta::new(self.find_treasury_address().0, false),
+            AccountMeta::new(self.treasury_ata, false),
+            AccountMeta::new_readonly(self.find_config_address().0, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(self.find_sub_bids_address(&subscription).0, false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new(self.find_user_address(user).0, false),
+        ];
+        accounts.extend(
+            sub_bids
+                .iter()
+                .flat_map(|sub_bids| &sub_bids.bids)
+                .flat_map(|ranked_bid| {
+                    [
+                        AccountMeta::new(
+                            self.find_bid_commit_address(&subscription, &ranked_bid.bidder)
+                                .0,
+                            false,
+                        ),
+                        AccountMeta::new(self.find_reputation_address(&ranked_bid.bidder).0, false),
+                    ]
+                }),
+        );
+
+        self.instruction(SmartVaultInstrunction::ReassignSub {}, accounts)
+    }
+
+    pub fn update_config(
+        &self,
+        admin: &Pubkey,
+        new_admin: &Pubkey,
+        params: ProtocolParams,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::UpdateConfig {
+                new_admin: *new_admin,
+                params,
+            },
+            vec![
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new(self.find_config_address().0, false),
+            ],
+        )
+    }
+
+    pub fn withdraw(&self, user: &Pubkey, user_ata: &Pubkey, amount: u64) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::Withdraw { amount },
+            vec![
+                AccountMeta::new_readonly(*user, true),
+                AccountMeta::new(*user_ata, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+        )
+    }
+
+    pub fn topup_sub(
+        &self,
+        user: &Pubkey,
+        user_ata: &Pubkey,
+        sub_id: u64,
+        amount: u64,
+    ) -> Instruction {
+        let subscription = self.find_subscription_address(user, sub_id).0;
+
+        self.instruction(
+            SmartVaultInstrunction::TopUpSub { amount },
+            vec![
+                AccountMeta::new(*user, true),
+                AccountMeta::new(*user_ata, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new(subscription, false),
+                AccountMeta::new(self.find_escrow_address(&subscription).0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    pub fn sweep_fees(&self, admin: &Pubkey, destination_ata: &Pubkey, amount: u64) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::SweepFees { amount },
+            vec![
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new(*destination_ata, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+        )
+    }
+
+    pub fn rotate_consensus(
+        &self,
+        next_signers: Vec<Pubkey>,
+        next_threshold: u8,
+        activation_epoch: u64,
+        expiry_slot: u64,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::RotateConsensus {
+                next_signers,
+                next_threshold,
+                activation_epoch,
+                expiry_slot,
+            },
+            vec![
+                AccountMeta::new(self.find_metadata_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(instructions::ID, false),
+            ],
+        )
+    }
+
+    pub fn set_measurement(
+        &self,
+        admin: &Pubkey,
+        measurement: [u8; 32],
+        approved: bool,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::SetMeasurement {
+                measurement,
+                approved,
+            },
+            vec![
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new(self.find_measurement_registry_address().0, false),
+            ],
+        )
+    }
+
+    /// Needs a consensus approval of [`attestation_commitment`] with the operator's current
+    /// attestation expiry as `value`.
+    pub fn re_attest(
+        &self,
+        operator: &Pubkey,
+        attestation_proof: String,
+        measurement: [u8; 32],
+        expiry_slot: u64,
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::ReAttest {
+                attestation_proof,
+                measurement,
+                expiry_slot,
+            },
+            vec![
+                AccountMeta::new(*operator, true),
+                AccountMeta::new(self.find_operator_address(operator).0, false),
+                AccountMeta::new_readonly(self.find_metadata_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(instructions::ID, false),
+            ],
+        )
+    }
+
+    pub fn re_attest_consensus(
+        &self,
+        admin: &Pubkey,
+        attestation_proof: String,
+        measurement: [u8; 32],
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::ReAttestConsensus {
+                attestation_proof,
+                measurement,
+            },
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new(self.find_metadata_address().0, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    /// Use [`bid_commitment`] to derive `commitment` from the amount revealed later.
+    pub fn commit_bid(
+        &self,
+        bidder: &Pubkey,
+        bidder_ata: &Pubkey,
+        subscription: &Pubkey,
+        commitment: [u8; 32],
+    ) -> Instruction {
+        self.instruction(
+            SmartVaultInstrunction::CommitBid { commitment },
+            vec![
+                AccountMeta::new(*bidder, true),
+                AccountMeta::new(*bidder_ata, false),
+                AccountMeta::new_readonly(self.find_operator_address(bidder).0, false),
+                AccountMeta::new(self.find_bid_commit_address(subscription, bidder).0, false),
+                AccountMeta::new_readonly(*subscription, false),
+                AccountMeta::new_readonly(self.find_measurement_registry_address().0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(self.find_config_address().0, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+
+    pub fn settle_bid(
+        &self,
+        bidder: &Pubkey,
+        bidder_ata: &Pubkey,
+        user: &Pubkey,
+        sub: &VaultUserSubscriptionState,
+    ) -> Instruction {
+        let subscription = self.find_subscription_address(user, sub.id).0;
+
+        self.instruction(
+            SmartVaultInstrunction::SettleBid {},
+            vec![
+                AccountMeta::new(*bidder, false),
+                AccountMeta::new(*bidder_ata, false),
+                AccountMeta::new(self.find_bid_commit_address(&subscription, bidder).0, false),
+                AccountMeta::new_readonly(subscription, false),
+                AccountMeta::new_readonly(self.find_app_address(sub.app_id).0, false),
+                AccountMeta::new_readonly(self.find_sub_bids_address(&subscription).0, false),
+                AccountMeta::new_readonly(*user, false),
+                AccountMeta::new(self.find_user_address(user).0, false),
+                AccountMeta::new_readonly(self.find_treasury_address().0, false),
+                AccountMeta::new(self.treasury_ata, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+        )
+    }
+
+    fn instruction(
+        &self,
+        instruction: SmartVaultInstrunction,
+        accounts: Vec<AccountMeta>,
+    ) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts,
+            data: instruction.pack(),
+        }
+    }
+}
+
+/// Sealed bid by default, call [`StartSubscriptionBuilder::auction_mode`] for a reverse Dutch auction.
+pub struct StartSubscriptionBuilder<'a> {
+    client: &'a VaultClient,
+    subscriber: Pubkey,
+    sub_id: u64,
+    app_id: u64,
+    max_rent: u64,
+    params_hash: String,
+    escrow_amount: u64,
+    auction_mode: AuctionMode,
+}
+
+impl StartSubscriptionBuilder<'_> {
+    pub fn auction_mode(mut self, auction_mode: AuctionMode) -> Self {
+        self.auction_mode = auction_mode;
+        self
+    }
+
+    pub fn build(self) -> Instruction {
+        let client = self.client;
+        let subscription = client
+            .find_subscription_address(&self.subscriber, self.sub_id)
+            .0;
+
+        client.instruction(
+            SmartVaultInstrunction::StartSubscription {
+                max_rent: self.max_rent,
+                app_id: self.app_id,
+                params_hash: self.params_hash,
+                escrow_amount: self.escrow_amount,
+                auction_mode: self.auction_mode,
+            },
+            vec![
+                AccountMeta::new(self.subscriber, true),
+                AccountMeta::new(client.find_user_address(&self.subscriber).0, false),
+                AccountMeta::new(subscription, false),
+                AccountMeta::new(client.find_escrow_address(&subscription).0, false),
+                AccountMeta::new_readonly(client.find_app_address(self.app_id).0, false),
+                AccountMeta::new_readonly(client.find_config_address().0, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+        )
+    }
+}
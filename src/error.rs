@@ -29,11 +29,58 @@ pub enum VaultError {
     InvalidConsesues,
     #[error("Signature verification failed.")]
     SigVerificationFailed,
+    #[error("Stake amount must be greater then zero")]
+    LessThenMinimumStakeAmount,
+    #[error("Bidder is not a registered operator with locked stake")]
+    OperatorNotStaked,
+    #[error("Rate can't be more then 10000 basis points")]
+    InvalidBasisPoints,
+    #[error("Executor is still within the reporting window")]
+    ExecutorWithinSla,
+    #[error("Subscription is not in restart phase")]
+    NotInRestartPhase,
+    #[error("Input is longer then allowed by protocol config")]
+    InputTooLong,
+    #[error("You are un-authorised to manage the protocol")]
+    UnAuthAdmin,
+    #[error("Max rent is below the app rent floor")]
+    RentBelowAppFloor,
+    #[error("Not enough accrued protocol fees")]
+    InsufficientAccruedFees,
+    #[error("Invalid consesues key rotation")]
+    InvalidKeyRotation,
+    #[error("Invalid consesues signer set or threshold")]
+    InvalidSignerSet,
+    #[error("Consesues approval expired")]
+    ConsesuesMessageExpired,
+    #[error("Enclave measurement not approved or revoked")]
+    MeasurementNotApproved,
+    #[error("Measurement registry is full")]
+    MeasurementRegistryFull,
+    #[error("Attestation expired, re-attest first")]
+    AttestationExpired,
+    #[error("Reveal window must be shorter then the bid window")]
+    InvalidSchedule,
+    #[error("Auction is not in commit phase")]
+    CommitPhaseOver,
+    #[error("Auction is not in reveal phase")]
+    NotInRevealPhase,
+    #[error("Revealed bid doesn't match the commitment")]
+    InvalidBidReveal,
+    #[error("Auction still running")]
+    AuctionNotSettled,
+    #[error("Higher ranked bidder can still claim")]
+    ClaimWindowNotOpen,
+    #[error("Not supported by the subscription's auction mode")]
+    InvalidAuctionMode,
+    #[error("Start rent can't be more then max rent")]
+    InvalidStartRent,
+    #[error("Current offer is below the bid")]
+    BidAboveOffer,
 }
 
-
 impl From<VaultError> for ProgramError {
     fn from(e: VaultError) -> Self {
         ProgramError::Custom(e as u32)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,161 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::AuctionMode;
+
+pub const EVENT_VERSION: u8 = 1;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum VaultEvent {
+    ProtocolInit {
+        admin: Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        measurement: [u8; 32],
+        attestation_proof: String,
+    },
+    ConfigUpdated {
+        admin: Pubkey,
+    },
+    OperatorJoined {
+        operator: Pubkey,
+        transit_key: Pubkey,
+        attestation_proof: String,
+        stake_amount: u64,
+    },
+    AppAdded {
+        app_id: u64,
+        creator_ata: Pubkey,
+        rent: u64,
+    },
+    ToppedUp {
+        user: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    Withdrawn {
+        user: Pubkey,
+        amount: u64,
+        balance: u64,
+    },
+    SubRequested {
+        subscriber: Pubkey,
+        subscription: Pubkey,
+        sub_id: u64,
+        max_rent: u64,
+        auction_mode: AuctionMode,
+        bid_endtime: u64,
+    },
+    SubToppedUp {
+        subscription: Pubkey,
+        amount: u64,
+        escrow_balance: u64,
+    },
+    BidCommitted {
+        subscription: Pubkey,
+        bidder: Pubkey,
+        round: u64,
+    },
+    BidAdded {
+        subscription: Pubkey,
+        bidder: Pubkey,
+        amount: u64,
+        leader: Pubkey,
+        rent: u64,
+    },
+    BidSettled {
+        subscription: Pubkey,
+        bidder: Pubkey,
+        bond: u64,
+        forfeited: bool,
+    },
+    BondForfeited {
+        subscription: Pubkey,
+        bidder: Pubkey,
+        bond: u64,
+    },
+    BidClaimed {
+        subscription: Pubkey,
+        executor: Pubkey,
+        rent: u64,
+        rank: u8,
+    },
+    WorkReported {
+        subscription: Pubkey,
+        executor: Pubkey,
+        nonce: u64,
+        executor_amount: u64,
+        creator_amount: u64,
+        protocol_fee: u64,
+    },
+    SubRestarted {
+        subscription: Pubkey,
+        executor: Pubkey,
+        reporter: Option<Pubkey>,
+    },
+    SubReassigned {
+        subscription: Pubkey,
+        round: u64,
+        max_rent: u64,
+        bid_endtime: u64,
+        keeper_bounty: u64,
+    },
+    SubClosed {
+        subscription: Pubkey,
+    },
+    ExecutorSlashed {
+        executor: Pubkey,
+        subscription: Pubkey,
+        reporter: Option<Pubkey>,
+        slashed_amount: u64,
+        subscriber_amount: u64,
+        reporter_amount: u64,
+        remaining_stake: u64,
+    },
+    ConsensusRotated {
+        next_signers: Vec<Pubkey>,
+        next_threshold: u8,
+        rotation_epoch: u64,
+        grace_end_epoch: u64,
+    },
+    MeasurementUpdated {
+        measurement: [u8; 32],
+        approved: bool,
+    },
+    OperatorReAttested {
+        operator: Pubkey,
+        attestation_expiry: u64,
+    },
+    ConsensusReAttested {
+        attestation_expiry: u64,
+    },
+    FeesSwept {
+        destination: Pubkey,
+        amount: u64,
+    },
+}
+
+/// Payload of every `Program data:` log line the program writes.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct VersionedEvent {
+    pub version: u8,
+    pub event: VaultEvent,
+}
+
+impl VaultEvent {
+    pub fn emit(self) {
+        let data = borsh::to_vec(&VersionedEvent {
+            version: EVENT_VERSION,
+            event: self,
+        })
+        .unwrap();
+        sol_log_data(&[&data]);
+    }
+}
+
+impl VersionedEvent {
+    // decoders should check the version before trusting the layout of newer events
+    pub fn decode(data: &[u8]) -> Result<Self, std::io::Error> {
+        Self::try_from_slice(data)
+    }
+}
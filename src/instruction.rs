@@ -1,151 +1,263 @@
-use borsh::BorshDeserialize;
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 
+use crate::state::{AppSchedule, AuctionMode, ProtocolParams};
+
+/// Encoded as the variant byte followed by the Borsh encoded fields. Discriminators are part of
+/// the wire format, new variants only get appended.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
 pub enum SmartVaultInstrunction {
     Init {
-        vault_public_key: Pubkey,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        measurement: [u8; 32],
         attestation_proof: String,
-    },
+        params: ProtocolParams,
+    } = 0,
     Join {
         attestation_proof: String,
+        measurement: [u8; 32],
         transit_key: Pubkey,
-    },
+        stake_amount: u64,
+    } = 1,
     AddApp {
         rent_amount: u64,
         ipfs_hash: String,
-    },
+        schedule: AppSchedule,
+    } = 2,
     TopUp {
         amount: u64,
-    },
+    } = 3,
     StartSubscription {
         max_rent: u64,
         app_id: u64,
         params_hash: String,
-    },
+        escrow_amount: u64,
+        auction_mode: AuctionMode,
+    } = 4,
     Bid {
-        _signature: [u8; 64],
         bid_amount: u64,
-    },
+        salt: [u8; 32],
+        expiry_slot: u64,
+    } = 5,
     ClaimBid {
-        _signature: [u8; 64],
-    },
+        expiry_slot: u64,
+    } = 6,
     ReportWork {
         nonce: u64,
-        _signature: [u8; 64],
-    },
-    CloseSub {},
+        expiry_slot: u64,
+    } = 7,
+    CloseSub {} = 8,
+    ReportFault {} = 9,
+    ReassignSub {} = 10,
+    UpdateConfig {
+        new_admin: Pubkey,
+        params: ProtocolParams,
+    } = 11,
+    Withdraw {
+        amount: u64,
+    } = 12,
+    TopUpSub {
+        amount: u64,
+    } = 13,
+    SweepFees {
+        amount: u64,
+    } = 14,
+    RotateConsensus {
+        next_signers: Vec<Pubkey>,
+        next_threshold: u8,
+        activation_epoch: u64,
+        expiry_slot: u64,
+    } = 15,
+    SetMeasurement {
+        measurement: [u8; 32],
+        approved: bool,
+    } = 16,
+    ReAttest {
+        attestation_proof: String,
+        measurement: [u8; 32],
+        expiry_slot: u64,
+    } = 17,
+    ReAttestConsensus {
+        attestation_proof: String,
+        measurement: [u8; 32],
+    } = 18,
+    CommitBid {
+        commitment: [u8; 32],
+    } = 19,
+    SettleBid {} = 20,
 }
 
-#[derive(Debug, BorshDeserialize)]
-struct InitPayload {
-    vault_public_key: Pubkey,
-    attestation_proof: String,
-}
+impl SmartVaultInstrunction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        Self::try_from_slice(input).map_err(|_| ProgramError::InvalidInstructionData)
+    }
 
-#[derive(BorshDeserialize)]
-struct JoinPayload {
-    attestation_proof: String,
-    transit_key: Pubkey,
+    pub fn pack(&self) -> Vec<u8> {
+        borsh::to_vec(self).unwrap()
+    }
 }
 
-#[derive(BorshDeserialize)]
-struct AddAppPayload {
-    rent_amount: u64,
-    ipfs_hash: String,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[derive(BorshDeserialize)]
-struct TopUpPayload {
-    amount: u64,
-}
+    fn params() -> ProtocolParams {
+        ProtocolParams {
+            bid_window: 100,
+            claim_window: 50,
+            report_interval: 60,
+            report_grace: 30,
+            min_topup: 10,
+            max_attestation_len: 64,
+            max_ipfs_hash_len: 64,
+            max_params_hash_len: 64,
+            slash_bps: 1_000,
+            reporter_share_bps: 5_000,
+            reputation_weight_bps: 100,
+            keeper_bounty: 5,
+            protocol_fee_bps: 500,
+            key_rotation_grace: 2,
+            attestation_ttl: 100_000,
+            reveal_window: 40,
+            bid_bond: 10,
+            max_schedule_window: 1_000_000,
+        }
+    }
 
-#[derive(BorshDeserialize)]
-struct StartSubscriptionPayload {
-    max_rent: u64,
-    app_id: u64,
-    params_hash: String,
-}
+    fn every_variant() -> Vec<SmartVaultInstrunction> {
+        vec![
+            SmartVaultInstrunction::Init {
+                signers: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                threshold: 2,
+                measurement: [7; 32],
+                attestation_proof: "consensus-proof".to_string(),
+                params: params(),
+            },
+            SmartVaultInstrunction::Join {
+                attestation_proof: "operator-proof".to_string(),
+                measurement: [7; 32],
+                transit_key: Pubkey::new_unique(),
+                stake_amount: 1_000,
+            },
+            SmartVaultInstrunction::AddApp {
+                rent_amount: 20,
+                ipfs_hash: "ipfs-hash".to_string(),
+                schedule: AppSchedule {
+                    report_interval: 60,
+                    grace_period: 30,
+                    bid_window: 100,
+                    claim_window: 50,
+                    reveal_window: 40,
+                },
+            },
+            SmartVaultInstrunction::TopUp { amount: 10 },
+            SmartVaultInstrunction::StartSubscription {
+                max_rent: 80,
+                app_id: 1,
+                params_hash: "params-hash".to_string(),
+                escrow_amount: 0,
+                auction_mode: AuctionMode::ReverseDutch { start_rent: 40 },
+            },
+            SmartVaultInstrunction::Bid {
+                bid_amount: 60,
+                salt: [42; 32],
+                expiry_slot: 12,
+            },
+            SmartVaultInstrunction::ClaimBid { expiry_slot: 12 },
+            SmartVaultInstrunction::ReportWork {
+                nonce: 3,
+                expiry_slot: 12,
+            },
+            SmartVaultInstrunction::CloseSub {},
+            SmartVaultInstrunction::ReportFault {},
+            SmartVaultInstrunction::ReassignSub {},
+            SmartVaultInstrunction::UpdateConfig {
+                new_admin: Pubkey::new_unique(),
+                params: params(),
+            },
+            SmartVaultInstrunction::Withdraw { amount: 5 },
+            SmartVaultInstrunction::TopUpSub { amount: 100 },
+            SmartVaultInstrunction::SweepFees { amount: 15 },
+            SmartVaultInstrunction::RotateConsensus {
+                next_signers: vec![Pubkey::new_unique()],
+                next_threshold: 1,
+                activation_epoch: 4,
+                expiry_slot: 12,
+            },
+            SmartVaultInstrunction::SetMeasurement {
+                measurement: [8; 32],
+                approved: true,
+            },
+            SmartVaultInstrunction::ReAttest {
+                attestation_proof: "operator-proof".to_string(),
+                measurement: [7; 32],
+                expiry_slot: 12,
+            },
+            SmartVaultInstrunction::ReAttestConsensus {
+                attestation_proof: "consensus-proof".to_string(),
+                measurement: [7; 32],
+            },
+            SmartVaultInstrunction::CommitBid {
+                commitment: [9; 32],
+            },
+            SmartVaultInstrunction::SettleBid {},
+        ]
+    }
 
-#[derive(BorshDeserialize)]
-struct BidPayload {
-    _signature: [u8; 64],
-    bid_amount: u64,
-}
+    #[test]
+    fn pack_round_trips_every_variant() {
+        for (discriminant, instruction) in every_variant().into_iter().enumerate() {
+            let packed = instruction.pack();
 
-#[derive(BorshDeserialize)]
-struct ClaimBidPayload {
-    _signature: [u8; 64],
-}
+            assert_eq!(packed[0] as usize, discriminant);
+            assert_eq!(SmartVaultInstrunction::unpack(&packed), Ok(instruction));
+        }
+    }
 
-#[derive(BorshDeserialize)]
-struct ReportWorkPayload {
-    nonce: u64,
-    _signature: [u8; 64],
-}
+    #[test]
+    fn pack_keeps_wire_format() {
+        let report_work = SmartVaultInstrunction::ReportWork {
+            nonce: 3,
+            expiry_slot: 258,
+        };
+        assert_eq!(
+            report_work.pack(),
+            [7, 3, 0, 0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0]
+        );
 
-impl SmartVaultInstrunction {
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (&variant, rest) = input
-            .split_first()
-            .ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(match variant {
-            0 => {
-                let payload = InitPayload::try_from_slice(rest).unwrap();
-                Self::Init {
-                    vault_public_key: payload.vault_public_key,
-                    attestation_proof: payload.attestation_proof,
-                }
-            }
-            1 => {
-                let payload = JoinPayload::try_from_slice(rest).unwrap();
-                Self::Join {
-                    attestation_proof: payload.attestation_proof,
-                    transit_key: payload.transit_key,
-                }
-            }
-            2 => {
-                let payload = AddAppPayload::try_from_slice(rest).unwrap();
-                Self::AddApp {
-                    rent_amount: payload.rent_amount,
-                    ipfs_hash: payload.ipfs_hash,
-                }
-            }
-            3 => {
-                let payload = TopUpPayload::try_from_slice(rest).unwrap();
-                Self::TopUp {
-                    amount: payload.amount,
-                }
-            }
-            4 => {
-                let payload = StartSubscriptionPayload::try_from_slice(rest).unwrap();
-                Self::StartSubscription {
-                    max_rent: payload.max_rent,
-                    app_id: payload.app_id,
-                    params_hash: payload.params_hash,
-                }
-            }
-            5 => {
-                let payload = BidPayload::try_from_slice(rest).unwrap();
-                Self::Bid {
-                    _signature: payload._signature,
-                    bid_amount: payload.bid_amount,
-                }
-            }
-            6 => {
-                let payload = ClaimBidPayload::try_from_slice(rest).unwrap();
-                Self::ClaimBid {
-                    _signature: payload._signature,
-                }
-            }
-            7 => {
-                let payload = ReportWorkPayload::try_from_slice(rest).unwrap();
-                Self::ReportWork {
-                    nonce: payload.nonce,
-                    _signature: payload._signature,
-                }
-            }
-            8 => Self::CloseSub {},
-            _ => return Err(ProgramError::InvalidInstructionData),
-        })
+        let claim_bid = SmartVaultInstrunction::ClaimBid { expiry_slot: 12 };
+        assert_eq!(claim_bid.pack(), [6, 12, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(SmartVaultInstrunction::SettleBid {}.pack(), [20]);
+    }
+
+    #[test]
+    fn unpack_rejects_unknown_discriminant() {
+        for discriminant in [21, u8::MAX] {
+            assert_eq!(
+                SmartVaultInstrunction::unpack(&[discriminant, 0, 0, 0, 0, 0, 0, 0, 0]),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_truncated_input() {
+        let packed = SmartVaultInstrunction::Bid {
+            bid_amount: 60,
+            salt: [42; 32],
+            expiry_slot: 12,
+        }
+        .pack();
+
+        for len in [0, 1, packed.len() - 1] {
+            assert_eq!(
+                SmartVaultInstrunction::unpack(&packed[..len]),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
     }
 }
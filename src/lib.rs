@@ -1,6 +1,10 @@
+#[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 pub mod utils;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod error;
+pub mod events;
 pub mod instruction;
 pub mod processor;
 pub mod state;
\ No newline at end of file
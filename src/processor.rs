@@ -3,6 +3,7 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     borsh1::try_from_slice_unchecked,
     entrypoint::ProgramResult,
+    hash::hashv,
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
@@ -14,23 +15,47 @@ use solana_program::{
 
 use crate::{
     error::VaultError,
+    events::VaultEvent,
     instruction::SmartVaultInstrunction,
     state::{
-        VaultAppCounterState, VaultAppState, VaultBidderState, VaultMetaDataState, VaultUserState,
-        VaultUserSubscriptionState,
+        AppSchedule, AuctionMode, ConsensusAction, ConsensusMessage, ProtocolConfig,
+        ProtocolParams, RankedBid, VaultAppCounterState, VaultAppState, VaultBidCommitState,
+        VaultBidderState, VaultMeasurementRegistryState, VaultMetaDataState, VaultOperatorState,
+        VaultReputationState, VaultSubBidsState, VaultSubEscrowState, VaultTreasuryState,
+        VaultUserState, VaultUserSubscriptionState,
+    },
+    utils::{
+        has_quorum, is_approved_measurement, is_ata_owner, is_attestation_lapsed,
+        is_eligible_operator, is_revoked_measurement, is_staked_operator, is_valid_consesues,
+        CONSENSUS_MSG_VERSION,
     },
-    utils::{is_ata_owner, is_valid_consesues},
 };
 
 use spl_token::{instruction::transfer, state::Account, ID as TOKEN_PROGRAM_ID};
 
-static VAULT_METADATA: &str = "METADATA";
-static APP_COUNTER: &str = "APP_COUNTER";
-static APP_STATE: &str = "APP_STATE";
-static USER_STATE: &str = "USER_STATE";
-static SUB_STATE: &str = "SUB_STATE";
-static TREASURY_STATE: &str = "TREASURY_STATE";
-static BIDDER_STATE: &str = "BIDDER_STATE";
+pub static VAULT_METADATA: &str = "METADATA";
+pub static APP_COUNTER: &str = "APP_COUNTER";
+pub static APP_STATE: &str = "APP_STATE";
+pub static USER_STATE: &str = "USER_STATE";
+pub static SUB_STATE: &str = "SUB_STATE";
+pub static TREASURY_STATE: &str = "TREASURY_STATE";
+pub static BIDDER_STATE: &str = "BIDDER_STATE";
+pub static OPERATOR_STATE: &str = "OPERATOR_STATE";
+pub static REPUTATION_STATE: &str = "REPUTATION_STATE";
+pub static PROTOCOL_CONFIG: &str = "PROTOCOL_CONFIG";
+pub static SUB_ESCROW: &str = "SUB_ESCROW";
+pub static MEASUREMENT_REGISTRY: &str = "MEASUREMENT_REGISTRY";
+pub static BID_COMMIT: &str = "BID_COMMIT";
+pub static SUB_BIDS: &str = "SUB_BIDS";
+
+const MAX_BPS: u16 = 10_000;
+const MAX_CONSENSUS_SIGNERS: usize = 10;
+const MAX_MEASUREMENTS: usize = 16;
+const MAX_RANKED_BIDS: usize = 5;
+const MAX_REPUTATION: u64 = 10_000;
+const INITIAL_REPUTATION: u64 = 5_000;
+const REPUTATION_REWARD: u64 = 10;
+const REPUTATION_PENALTY: u64 = 500;
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -40,42 +65,121 @@ pub fn process_instruction(
     let instuction = SmartVaultInstrunction::unpack(instruction_data)?;
     match instuction {
         SmartVaultInstrunction::Init {
-            vault_public_key,
+            signers,
+            threshold,
+            measurement,
+            attestation_proof,
+            params,
+        } => init(
+            program_id,
+            accounts,
+            signers,
+            threshold,
+            measurement,
             attestation_proof,
-        } => init(program_id, accounts, &vault_public_key, attestation_proof),
+            params,
+        ),
         SmartVaultInstrunction::Join {
             attestation_proof,
+            measurement,
             transit_key,
-        } => join(&transit_key, attestation_proof),
+            stake_amount,
+        } => join(
+            program_id,
+            accounts,
+            &transit_key,
+            attestation_proof,
+            measurement,
+            stake_amount,
+        ),
         SmartVaultInstrunction::AddApp {
             rent_amount,
             ipfs_hash,
-        } => add_app(program_id, accounts, ipfs_hash, rent_amount),
+            schedule,
+        } => add_app(program_id, accounts, ipfs_hash, rent_amount, schedule),
         SmartVaultInstrunction::Bid {
-            _signature,
             bid_amount,
-        } => bid(program_id, accounts, _signature, bid_amount),
-        SmartVaultInstrunction::ClaimBid { _signature } => {
-            claimbid(program_id, accounts, _signature)
+            salt,
+            expiry_slot,
+        } => bid(program_id, accounts, bid_amount, salt, expiry_slot),
+        SmartVaultInstrunction::CommitBid { commitment } => {
+            commit_bid(program_id, accounts, commitment)
+        }
+        SmartVaultInstrunction::SettleBid {} => settle_bid(program_id, accounts),
+        SmartVaultInstrunction::ClaimBid { expiry_slot } => {
+            claimbid(program_id, accounts, expiry_slot)
         }
         SmartVaultInstrunction::CloseSub {} => close_sub(program_id, accounts),
-        SmartVaultInstrunction::ReportWork { nonce, _signature } => {
-            report_work(program_id, accounts, nonce, _signature)
+        SmartVaultInstrunction::ReportWork { nonce, expiry_slot } => {
+            report_work(program_id, accounts, nonce, expiry_slot)
         }
         SmartVaultInstrunction::StartSubscription {
             max_rent,
             app_id,
             params_hash,
-        } => start_subscription(program_id, accounts, max_rent, app_id, params_hash),
+            escrow_amount,
+            auction_mode,
+        } => start_subscription(
+            program_id,
+            accounts,
+            max_rent,
+            app_id,
+            params_hash,
+            escrow_amount,
+            auction_mode,
+        ),
         SmartVaultInstrunction::TopUp { amount } => topup(program_id, accounts, amount),
+        SmartVaultInstrunction::TopUpSub { amount } => topup_sub(program_id, accounts, amount),
+        SmartVaultInstrunction::SweepFees { amount } => sweep_fees(program_id, accounts, amount),
+        SmartVaultInstrunction::RotateConsensus {
+            next_signers,
+            next_threshold,
+            activation_epoch,
+            expiry_slot,
+        } => rotate_consensus(
+            program_id,
+            accounts,
+            next_signers,
+            next_threshold,
+            activation_epoch,
+            expiry_slot,
+        ),
+        SmartVaultInstrunction::SetMeasurement {
+            measurement,
+            approved,
+        } => set_measurement(program_id, accounts, measurement, approved),
+        SmartVaultInstrunction::ReAttest {
+            attestation_proof,
+            measurement,
+            expiry_slot,
+        } => re_attest(
+            program_id,
+            accounts,
+            attestation_proof,
+            measurement,
+            expiry_slot,
+        ),
+        SmartVaultInstrunction::ReAttestConsensus {
+            attestation_proof,
+            measurement,
+        } => re_attest_consensus(program_id, accounts, attestation_proof, measurement),
+        SmartVaultInstrunction::ReportFault {} => report_fault(program_id, accounts),
+        SmartVaultInstrunction::ReassignSub {} => reassign_sub(program_id, accounts),
+        SmartVaultInstrunction::UpdateConfig { new_admin, params } => {
+            update_config(program_id, accounts, &new_admin, params)
+        }
+        SmartVaultInstrunction::Withdraw { amount } => withdraw(program_id, accounts, amount),
     }
 }
 
 pub fn init(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    vault_public_key: &Pubkey,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+    measurement: [u8; 32],
     attestation_proof: String,
+    params: ProtocolParams,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -83,9 +187,11 @@ pub fn init(
     let pda_account = next_account_info(account_info_iter)?;
     let app_counter = next_account_info(account_info_iter)?;
     let program_treasury = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
-    // TODO: in future add logic of consesues rolling. Also integrate chainlink functions for attestation verification
+    // TODO: integrate chainlink functions for attestation verification
     if !initializer.is_signer {
         msg!("Missing required signature");
         return Err(ProgramError::MissingRequiredSignature);
@@ -114,7 +220,31 @@ pub fn init(
         return Err(VaultError::InvalidPDA.into());
     }
 
-    let state_size = 1 + 32 + (4 + attestation_proof.len());
+    let (protocol_config_pda, _config_bump_seed) =
+        Pubkey::find_program_address(&[PROTOCOL_CONFIG.as_bytes()], program_id);
+
+    if protocol_config_pda != *protocol_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let (measurement_registry_pda, _registry_bump_seed) =
+        Pubkey::find_program_address(&[MEASUREMENT_REGISTRY.as_bytes()], program_id);
+
+    if measurement_registry_pda != *measurement_registry.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    check_params(&params)?;
+    check_signer_set(&signers, threshold)?;
+
+    if attestation_proof.len() > params.max_attestation_len as usize {
+        msg!("Attestation proof too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
+    let state_size = metadata_size(attestation_proof.len());
     let rent = Rent::get()?;
     let rent_lamports = rent.minimum_balance(state_size);
 
@@ -154,7 +284,7 @@ pub fn init(
         &[&[APP_COUNTER.as_bytes(), &[_counter_bump_seed]]],
     )?;
 
-    let state_size = 0;
+    let state_size = 1 + 8 + 8;
     let rent = Rent::get()?;
     let rent_lamports = rent.minimum_balance(state_size);
 
@@ -174,6 +304,46 @@ pub fn init(
         &[&[TREASURY_STATE.as_bytes(), &[_treasury_bump_seed]]],
     )?;
 
+    let state_size =
+        1 + 32 + (8 + 8 + 8 + 8 + 8 + 4 + 4 + 4 + 2 + 2 + 2 + 8 + 2 + 8 + 8 + 8 + 8 + 8);
+    let rent = Rent::get()?;
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            initializer.key,
+            protocol_config.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[
+            initializer.clone(),
+            protocol_config.clone(),
+            system_program.clone(),
+        ],
+        &[&[PROTOCOL_CONFIG.as_bytes(), &[_config_bump_seed]]],
+    )?;
+
+    let state_size = 1 + (4 + 32 * MAX_MEASUREMENTS);
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            initializer.key,
+            measurement_registry.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[
+            initializer.clone(),
+            measurement_registry.clone(),
+            system_program.clone(),
+        ],
+        &[&[MEASUREMENT_REGISTRY.as_bytes(), &[_registry_bump_seed]]],
+    )?;
+
     let mut account_data =
         try_from_slice_unchecked::<VaultMetaDataState>(&pda_account.data.borrow()).unwrap();
 
@@ -189,21 +359,277 @@ pub fn init(
         msg!("App counter acc already exsist!");
         return Err(ProgramError::AccountAlreadyInitialized);
     }
-    msg!("ProtocolInit:{}:{}", vault_public_key, attestation_proof);
+    VaultEvent::ProtocolInit {
+        admin: *initializer.key,
+        signers: signers.clone(),
+        threshold,
+        measurement,
+        attestation_proof: attestation_proof.clone(),
+    }
+    .emit();
     account_data.attestation_proof = attestation_proof;
-    account_data.vault_public_key = *vault_public_key;
+    account_data.signers = signers;
+    account_data.threshold = threshold;
+    account_data.measurement = measurement;
+    account_data.attestation_expiry = Clock::get()?.unix_timestamp as u64 + params.attestation_ttl;
     account_data.is_initialized = true;
     account_data.serialize(&mut &mut pda_account.data.borrow_mut()[..])?;
 
+    let protocol_config_data = ProtocolConfig {
+        is_initialized: true,
+        admin: *initializer.key,
+        params,
+    };
+    protocol_config_data.serialize(&mut &mut protocol_config.data.borrow_mut()[..])?;
+
     app_counter_data.is_initialized = true;
     app_counter_data.serialize(&mut &mut app_counter.data.borrow_mut()[..])?;
 
+    let program_treasury_data = VaultTreasuryState {
+        is_initialized: true,
+        accrued_fees: 0,
+        swept_fees: 0,
+    };
+    program_treasury_data.serialize(&mut &mut program_treasury.data.borrow_mut()[..])?;
+
+    // the consensus enclave build is approved up front so the set can sign right away
+    let measurement_registry_data = VaultMeasurementRegistryState {
+        is_initialized: true,
+        measurements: vec![measurement],
+    };
+    measurement_registry_data.serialize(&mut &mut measurement_registry.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_admin: &Pubkey,
+    params: ProtocolParams,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+
+    if !admin.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if protocol_config_data.admin != *admin.key {
+        msg!("Only protocol admin can update config");
+        return Err(VaultError::UnAuthAdmin.into());
+    }
+
+    check_params(&params)?;
+
+    protocol_config_data.admin = *new_admin;
+    protocol_config_data.params = params;
+    protocol_config_data.serialize(&mut &mut protocol_config.data.borrow_mut()[..])?;
+
+    VaultEvent::ConfigUpdated { admin: *new_admin }.emit();
+
     Ok(())
 }
 
-pub fn join(transit_key: &Pubkey, attestation_proof: String) -> ProgramResult {
-    // TODO: on join, server providers need to lock some amount of token and there will be state associated with their acc to maintain reputation and locked tokens
-    msg!("JoinReq:{}:{}", transit_key, attestation_proof);
+pub fn join(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    transit_key: &Pubkey,
+    attestation_proof: String,
+    measurement: [u8; 32],
+    stake_amount: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let operator = next_account_info(account_info_iter)?;
+    let operator_ata = next_account_info(account_info_iter)?;
+    let operator_state = next_account_info(account_info_iter)?;
+    let operator_reputation = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !operator.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(operator.key, operator_ata) {
+        msg!("Wrong ata provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if program_treasury.owner != program_id {
+        msg!("Wrong treasury account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (program_treasury_pda, _) =
+        Pubkey::find_program_address(&[TREASURY_STATE.as_bytes()], program_id);
+
+    if program_treasury_pda != *program_treasury.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if !is_ata_owner(program_treasury.key, program_ata) {
+        msg!("Wrong treasury ata account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if attestation_proof.len() > protocol_config_data.params.max_attestation_len as usize {
+        msg!("Attestation proof too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
+    is_approved_measurement(
+        MEASUREMENT_REGISTRY,
+        measurement_registry,
+        &measurement,
+        program_id,
+    )?;
+
+    if stake_amount == 0 {
+        msg!("Stake amount can't be zero");
+        return Err(VaultError::LessThenMinimumStakeAmount.into());
+    }
+
+    let operator_ata_data = Account::unpack(&operator_ata.data.borrow())?;
+    if operator_ata_data.amount < stake_amount {
+        msg!("Insufficient funds to stake!");
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let (operator_state_pda, _bump_seed) = Pubkey::find_program_address(
+        &[OPERATOR_STATE.as_bytes(), operator.key.as_ref()],
+        program_id,
+    );
+
+    if operator_state_pda != *operator_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let state_size = operator_size(attestation_proof.len());
+    let rent = Rent::get()?;
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            operator.key,
+            operator_state.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[
+            operator.clone(),
+            operator_state.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            OPERATOR_STATE.as_bytes(),
+            operator.key.as_ref(),
+            &[_bump_seed],
+        ]],
+    )?;
+
+    let mut operator_state_data =
+        try_from_slice_unchecked::<VaultOperatorState>(&operator_state.data.borrow()).unwrap();
+
+    if operator_state_data.is_initialized() {
+        msg!("Operator already joined!");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (operator_reputation_pda, _reputation_bump_seed) = Pubkey::find_program_address(
+        &[REPUTATION_STATE.as_bytes(), operator.key.as_ref()],
+        program_id,
+    );
+
+    if operator_reputation_pda != *operator_reputation.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let state_size = 1 + 8 + 8 + 8;
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            operator.key,
+            operator_reputation.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[
+            operator.clone(),
+            operator_reputation.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            REPUTATION_STATE.as_bytes(),
+            operator.key.as_ref(),
+            &[_reputation_bump_seed],
+        ]],
+    )?;
+
+    let mut operator_reputation_data =
+        try_from_slice_unchecked::<VaultReputationState>(&operator_reputation.data.borrow())
+            .unwrap();
+
+    let transfer_stake_to_programm = transfer(
+        &TOKEN_PROGRAM_ID,
+        operator_ata.key,
+        program_ata.key,
+        operator.key,
+        &[],
+        stake_amount,
+    )?;
+
+    invoke(
+        &transfer_stake_to_programm,
+        &[
+            operator_ata.clone(),
+            program_ata.clone(),
+            operator.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    let clock = Clock::get()?;
+    VaultEvent::OperatorJoined {
+        operator: *operator.key,
+        transit_key: *transit_key,
+        attestation_proof: attestation_proof.clone(),
+        stake_amount,
+    }
+    .emit();
+    operator_state_data.is_initialized = true;
+    operator_state_data.attestation_proof = attestation_proof;
+    operator_state_data.transit_key = *transit_key;
+    operator_state_data.stake = stake_amount;
+    operator_state_data.registered_at = clock.unix_timestamp as u64;
+    operator_state_data.measurement = measurement;
+    operator_state_data.attestation_expiry =
+        clock.unix_timestamp as u64 + protocol_config_data.params.attestation_ttl;
+    operator_state_data.serialize(&mut &mut operator_state.data.borrow_mut()[..])?;
+
+    operator_reputation_data.is_initialized = true;
+    operator_reputation_data.score = INITIAL_REPUTATION;
+    operator_reputation_data.serialize(&mut &mut operator_reputation.data.borrow_mut()[..])?;
+
     Ok(())
 }
 
@@ -212,6 +638,7 @@ pub fn add_app(
     accounts: &[AccountInfo],
     ipfs_hash: String,
     rent_amount: u64,
+    schedule: AppSchedule,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
@@ -219,6 +646,7 @@ pub fn add_app(
     let creator_ata = next_account_info(account_info_iter)?;
     let app_counter = next_account_info(account_info_iter)?;
     let app_state = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
     // TODO: if app is private/permissioned following logic will be followed. For app going to visible in public marketplace they should be included with dao or community voting approval
@@ -233,6 +661,13 @@ pub fn add_app(
         return Err(ProgramError::InvalidAccountOwner);
     }
 
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if ipfs_hash.len() > protocol_config_data.params.max_ipfs_hash_len as usize {
+        msg!("Ipfs hash too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
     let (app_id_counter_pda, _) =
         Pubkey::find_program_address(&[APP_COUNTER.as_bytes()], program_id);
 
@@ -262,14 +697,35 @@ pub fn add_app(
         return Err(VaultError::InvalidPDA.into());
     }
 
-    let state_size = 1 + (4 + ipfs_hash.len()) + 8 + 32;
+    // zero values fall back to the protocol wide defaults
+    let params = &protocol_config_data.params;
+    let schedule = AppSchedule {
+        report_interval: or_default(schedule.report_interval, params.report_interval),
+        grace_period: or_default(schedule.grace_period, params.report_grace),
+        bid_window: or_default(schedule.bid_window, params.bid_window),
+        claim_window: or_default(schedule.claim_window, params.claim_window),
+        reveal_window: or_default(schedule.reveal_window, params.reveal_window),
+    };
+
+    if !within_schedule_cap(&schedule, params.max_schedule_window) {
+        msg!("Schedule windows exceed the protocol maximum");
+        return Err(VaultError::InvalidSchedule.into());
+    }
+
+    // commits need some time before the reveal phase opens
+    if schedule.reveal_window >= schedule.bid_window {
+        msg!("Reveal window must leave room for commits");
+        return Err(VaultError::InvalidSchedule.into());
+    }
+
+    let state_size = 1 + (4 + ipfs_hash.len()) + 8 + 32 + (8 + 8 + 8 + 8 + 8);
     let rent = Rent::get()?;
     let rent_lamports = rent.minimum_balance(state_size);
 
     invoke_signed(
         &system_instruction::create_account(
             creator.key,
-            &app_state.key,
+            app_state.key,
             rent_lamports,
             state_size.try_into().unwrap(),
             program_id,
@@ -294,8 +750,16 @@ pub fn add_app(
     app_state_data.ipfs_hash = ipfs_hash;
     app_state_data.rent = rent_amount;
     app_state_data.creator_ata = *creator_ata.key;
+    app_state_data.schedule = schedule;
     app_state_data.serialize(&mut &mut app_state.data.borrow_mut()[..])?;
 
+    VaultEvent::AppAdded {
+        app_id: app_counter_data.counter,
+        creator_ata: *creator_ata.key,
+        rent: rent_amount,
+    }
+    .emit();
+
     app_counter_data.counter += 1;
     app_counter_data.serialize(&mut &mut app_counter.data.borrow_mut()[..])?;
 
@@ -310,6 +774,7 @@ pub fn topup(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> Prog
     let user_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
     let program_treasury = next_account_info(account_info_iter)?;
     let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
@@ -341,8 +806,10 @@ pub fn topup(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> Prog
         return Err(ProgramError::InvalidAccountOwner);
     }
 
-    if amount <= 0 {
-        msg!("Amount can't be less then eq to zero");
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if amount == 0 || amount < protocol_config_data.params.min_topup {
+        msg!("Amount can't be less then minimum topup");
         return Err(VaultError::LessThenMinimumTopupAmount.into());
     }
 
@@ -360,15 +827,15 @@ pub fn topup(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> Prog
         return Err(VaultError::InvalidPDA.into());
     }
 
-    if user_state.data.borrow().len() <= 0 {
-        let state_size = 1 + 8 + 8;
+    if user_state.data.borrow().is_empty() {
+        let state_size = 1 + 8 + 8 + 8;
         let rent = Rent::get()?;
         let rent_lamports = rent.minimum_balance(state_size);
 
         invoke_signed(
             &system_instruction::create_account(
                 user.key,
-                &user_state.key,
+                user_state.key,
                 rent_lamports,
                 state_size.try_into().unwrap(),
                 program_id,
@@ -409,6 +876,13 @@ pub fn topup(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> Prog
     user_state_data.is_initialized = true;
     user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
 
+    VaultEvent::ToppedUp {
+        user: *user.key,
+        amount,
+        balance: user_state_data.balance,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -418,13 +892,17 @@ pub fn start_subscription(
     max_rent: u64,
     app_id: u64,
     params_hash: String,
+    escrow_amount: u64,
+    auction_mode: AuctionMode,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
     let subscriber = next_account_info(account_info_iter)?;
     let subscriber_state = next_account_info(account_info_iter)?;
     let subscriber_sub_state = next_account_info(account_info_iter)?;
+    let subscriber_sub_escrow = next_account_info(account_info_iter)?;
     let app_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
     if !subscriber.is_signer {
@@ -442,6 +920,13 @@ pub fn start_subscription(
         return Err(ProgramError::InvalidAccountOwner);
     }
 
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if params_hash.len() > protocol_config_data.params.max_params_hash_len as usize {
+        msg!("Params hash too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
     let (app_state_pda, _) = Pubkey::find_program_address(
         &[APP_STATE.as_bytes(), app_id.to_be_bytes().as_ref()],
         program_id,
@@ -475,7 +960,31 @@ pub fn start_subscription(
         return Err(ProgramError::UninitializedAccount);
     }
 
-    if subscriber_state_data.balance < max_rent {
+    if max_rent < app_state_data.rent {
+        msg!("max rent is below the app's rent floor");
+        return Err(VaultError::RentBelowAppFloor.into());
+    }
+
+    if matches!(auction_mode, AuctionMode::ReverseDutch { start_rent } if start_rent > max_rent) {
+        msg!("start rent is above max rent");
+        return Err(VaultError::InvalidStartRent.into());
+    }
+
+    // every cycle pays the executor and the app creator
+    let cycle_rent = max_rent
+        .checked_add(app_state_data.rent)
+        .ok_or(VaultError::InefficientBalance)?;
+
+    let available_balance = subscriber_state_data
+        .balance
+        .saturating_sub(subscriber_state_data.reserved);
+    // an escrowed subscription is funded up front from the pooled balance instead of reserving it
+    if escrow_amount > 0 && escrow_amount < cycle_rent {
+        msg!("escrow should cover rent for atleast one cycle");
+        return Err(VaultError::InefficientBalance.into());
+    }
+
+    if available_balance < cycle_rent.max(escrow_amount) {
         msg!("topup account to provide rent for atleast one cycle");
         return Err(VaultError::InefficientBalance.into());
     }
@@ -494,14 +1003,34 @@ pub fn start_subscription(
         return Err(VaultError::InvalidPDA.into());
     }
 
-    let state_size = 8 + 1 + 1 + 8 + (4 + params_hash.len()) + 8 + 1 + 32 + 8 + 8 + 8 + 8 + 1;
+    let state_size = 8
+        + 1
+        + 1
+        + 8
+        + (4 + params_hash.len())
+        + 8
+        + 1
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 1
+        + (1 + 8)
+        + 8;
     let rent = Rent::get()?;
     let rent_lamports = rent.minimum_balance(state_size);
 
     invoke_signed(
         &system_instruction::create_account(
             subscriber.key,
-            &subscriber_sub_state.key,
+            subscriber_sub_state.key,
             rent_lamports,
             state_size.try_into().unwrap(),
             program_id,
@@ -528,6 +1057,24 @@ pub fn start_subscription(
         return Err(ProgramError::AccountAlreadyInitialized);
     }
 
+    if escrow_amount > 0 {
+        let mut subscriber_sub_escrow_data = create_escrow(
+            program_id,
+            subscriber,
+            subscriber_sub_state,
+            subscriber_sub_escrow,
+            system_program,
+        )?;
+        subscriber_sub_escrow_data.balance = escrow_amount;
+        subscriber_sub_escrow_data
+            .serialize(&mut &mut subscriber_sub_escrow.data.borrow_mut()[..])?;
+
+        subscriber_sub_state_data.has_escrow = true;
+        subscriber_state_data.balance -= escrow_amount;
+    } else {
+        subscriber_state_data.reserved += cycle_rent;
+    }
+
     let clock = Clock::get()?;
     subscriber_sub_state_data.id = subscriber_state_data.count;
     subscriber_sub_state_data.app_id = app_id;
@@ -535,20 +1082,28 @@ pub fn start_subscription(
     subscriber_sub_state_data.params_hash = params_hash;
     subscriber_sub_state_data.max_rent = max_rent;
     subscriber_sub_state_data.rent = max_rent;
-    subscriber_sub_state_data.bid_endtime = clock.unix_timestamp as u64 + 60;
+    subscriber_sub_state_data.creator_rent = app_state_data.rent;
+    subscriber_sub_state_data.auction_mode = auction_mode;
+    open_auction(
+        &mut subscriber_sub_state_data,
+        &app_state_data.schedule,
+        clock.unix_timestamp as u64,
+    )?;
 
     subscriber_sub_state_data.serialize(&mut &mut subscriber_sub_state.data.borrow_mut()[..])?;
 
     subscriber_state_data.count += 1;
     subscriber_state_data.serialize(&mut &mut subscriber_state.data.borrow_mut()[..])?;
 
-    msg!(
-        "SubRequest:{}:{}:{}:{}",
-        subscriber.key,
-        subscriber_state_data.count - 1,
+    VaultEvent::SubRequested {
+        subscriber: *subscriber.key,
+        subscription: *subscriber_sub_state.key,
+        sub_id: subscriber_sub_state_data.id,
         max_rent,
-        subscriber_sub_state_data.bid_endtime
-    );
+        auction_mode,
+        bid_endtime: subscriber_sub_state_data.bid_endtime,
+    }
+    .emit();
 
     Ok(())
 }
@@ -556,16 +1111,22 @@ pub fn start_subscription(
 pub fn bid(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _signature: [u8; 64],
     bid_amount: u64,
+    salt: [u8; 32],
+    expiry_slot: u64,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    let consensus = next_account_info(account_info_iter)?;
     let bidder = next_account_info(account_info_iter)?;
     let bidder_state = next_account_info(account_info_iter)?;
+    let bidder_operator = next_account_info(account_info_iter)?;
+    let bidder_reputation = next_account_info(account_info_iter)?;
+    let bid_commit = next_account_info(account_info_iter)?;
+    let sub_bids = next_account_info(account_info_iter)?;
     let sub_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
     let metadata: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
     let ix_sysvar: &AccountInfo<'_> = next_account_info(account_info_iter)?;
 
@@ -579,6 +1140,20 @@ pub fn bid(
         return Err(ProgramError::InvalidAccountOwner);
     }
 
+    let bidder_operator_data = is_eligible_operator(
+        OPERATOR_STATE,
+        MEASUREMENT_REGISTRY,
+        bidder.key,
+        bidder_operator,
+        measurement_registry,
+        program_id,
+    )?;
+
+    if is_attestation_lapsed(bidder_operator_data.attestation_expiry)? {
+        msg!("Operator attestation lapsed, re-attest before bidding");
+        return Err(VaultError::AttestationExpired.into());
+    }
+
     let (bidder_state_pda, _bump_seed) =
         Pubkey::find_program_address(&[BIDDER_STATE.as_bytes(), bidder.key.as_ref()], program_id);
 
@@ -587,7 +1162,7 @@ pub fn bid(
         return Err(VaultError::InvalidPDA.into());
     }
 
-    if bidder_state.data.borrow().len() <= 0 {
+    if bidder_state.data.borrow().is_empty() {
         let state_size = 1 + 8;
         let rent = Rent::get()?;
         let rent_lamports = rent.minimum_balance(state_size);
@@ -595,7 +1170,7 @@ pub fn bid(
         invoke_signed(
             &system_instruction::create_account(
                 bidder.key,
-                &bidder_state.key,
+                bidder_state.key,
                 rent_lamports,
                 state_size.try_into().unwrap(),
                 program_id,
@@ -613,18 +1188,25 @@ pub fn bid(
     let mut bidder_state_data =
         try_from_slice_unchecked::<VaultBidderState>(&bidder_state.data.borrow()).unwrap();
 
-    let mut raw_message: [u8; 40] = [0; 40];
-    raw_message[..32].copy_from_slice(bidder.key.to_bytes().as_ref());
-    raw_message[32..].copy_from_slice(bidder_state_data.nonce.to_be_bytes().as_ref());
+    let message = ConsensusMessage {
+        version: CONSENSUS_MSG_VERSION,
+        action: ConsensusAction::Bid,
+        program_id: *program_id,
+        subscription: *sub_state.key,
+        operator: *bidder.key,
+        operator_nonce: bidder_state_data.nonce,
+        value: bid_amount,
+        expiry_slot,
+    };
 
     is_valid_consesues(
         VAULT_METADATA,
+        MEASUREMENT_REGISTRY,
         ix_sysvar,
-        consensus,
         metadata,
+        measurement_registry,
         program_id,
-        raw_message.as_ref(),
-        _signature.as_ref(),
+        &message,
     )?;
 
     let mut sub_state_data =
@@ -635,47 +1217,467 @@ pub fn bid(
         return Err(ProgramError::UninitializedAccount);
     }
 
+    let bidder_reputation_data = load_reputation(program_id, bidder_reputation, bidder.key)?;
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
     let clock = Clock::get()?;
     let cur_time = clock.unix_timestamp as u64;
 
-    // TODO: Currently least rent amount bidder wins. But in future apart from least rent winner should be also selected by keeping reputation factor in mind.
-    if cur_time < sub_state_data.bid_endtime {
-        if bid_amount < sub_state_data.rent {
-            sub_state_data.executor = *bidder.key;
-            sub_state_data.rent = bid_amount;
-        } else if bid_amount == sub_state_data.rent {
-            if sub_state_data.executor == *system_program.key {
-                sub_state_data.executor = *bidder.key;
-            }
+    // first operator to accept the rising offer takes the job, no commitment or claim needed
+    if let AuctionMode::ReverseDutch { start_rent } = sub_state_data.auction_mode {
+        if sub_state_data.closed {
+            msg!("subscription already closed!");
+            return Err(VaultError::SubScriptionClosed.into());
         }
-    } else {
-        msg!("Bid time expired");
-        return Err(VaultError::BidTimeExpired.into());
-    }
 
-    bidder_state_data.is_initialized = true;
-    bidder_state_data.nonce += 1;
-    bidder_state_data.serialize(&mut &mut bidder_state.data.borrow_mut()[..])?;
+        if sub_state_data.restart {
+            msg!("Its currently on re-assign mode, wait for the next auction round!");
+            return Err(VaultError::RestartPhase.into());
+        }
 
-    sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
+        if sub_state_data.is_assigned {
+            msg!("Offer already taken!");
+            return Err(VaultError::BidAlreadyClaimed.into());
+        }
 
-    msg!("BidAdded:{}:{}", sub_state.key, sub_state_data.rent);
-    Ok(())
-}
+        // an offer nobody took by the end of the window is reopened through reassign_sub
+        if cur_time >= sub_state_data.bid_endtime {
+            msg!("Bid time expired");
+            return Err(VaultError::BidTimeExpired.into());
+        }
+
+        let offer = dutch_offer(&sub_state_data, start_rent, cur_time);
+        if bid_amount > offer {
+            msg!("Current offer is below the bid");
+            return Err(VaultError::BidAboveOffer.into());
+        }
+
+        sub_state_data.executor = *bidder.key;
+        sub_state_data.rent = offer;
+        sub_state_data.bid_score = bid_score(
+            offer,
+            sub_state_data.max_rent,
+            bidder_reputation_data.score,
+            protocol_config_data.params.reputation_weight_bps,
+        );
+        sub_state_data.is_assigned = true;
+        sub_state_data.last_report_time = cur_time;
+        sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
+
+        bidder_state_data.is_initialized = true;
+        bidder_state_data.nonce += 1;
+        bidder_state_data.serialize(&mut &mut bidder_state.data.borrow_mut()[..])?;
+
+        VaultEvent::BidAdded {
+            subscription: *sub_state.key,
+            bidder: *bidder.key,
+            amount: offer,
+            leader: *bidder.key,
+            rent: sub_state_data.rent,
+        }
+        .emit();
+        VaultEvent::BidClaimed {
+            subscription: *sub_state.key,
+            executor: *bidder.key,
+            rent: sub_state_data.rent,
+            rank: 0,
+        }
+        .emit();
+        return Ok(());
+    }
+
+    let mut bid_commit_data = load_bid_commit(program_id, bid_commit, sub_state.key, bidder.key)?;
+
+    if bid_commit_data.round != sub_state_data.auction_round || bid_commit_data.revealed {
+        msg!("No open commitment for this auction");
+        return Err(VaultError::InvalidBidReveal.into());
+    }
+
+    if bid_commitment(bid_amount, &salt, bidder.key) != bid_commit_data.commitment {
+        msg!("Bid amount or salt doesn't match commitment");
+        return Err(VaultError::InvalidBidReveal.into());
+    }
+
+    if cur_time < sub_state_data.reveal_time {
+        msg!("Reveal phase not started yet");
+        return Err(VaultError::NotInRevealPhase.into());
+    }
+
+    if cur_time < sub_state_data.bid_endtime {
+        if bid_amount <= sub_state_data.max_rent {
+            let score = bid_score(
+                bid_amount,
+                sub_state_data.max_rent,
+                bidder_reputation_data.score,
+                protocol_config_data.params.reputation_weight_bps,
+            );
+
+            let mut sub_bids_data = load_or_create_sub_bids(
+                program_id,
+                bidder,
+                sub_state,
+                sub_bids,
+                system_program,
+                sub_state_data.auction_round,
+            )?;
+            rank_bid(
+                &mut sub_bids_data.bids,
+                RankedBid {
+                    bidder: *bidder.key,
+                    amount: bid_amount,
+                    score,
+                },
+            );
+            sub_bids_data.serialize(&mut &mut sub_bids.data.borrow_mut()[..])?;
+
+            let leader = &sub_bids_data.bids[0];
+            sub_state_data.executor = leader.bidder;
+            sub_state_data.rent = leader.amount;
+            sub_state_data.bid_score = leader.score;
+        }
+    } else {
+        msg!("Bid time expired");
+        return Err(VaultError::BidTimeExpired.into());
+    }
+
+    bidder_state_data.is_initialized = true;
+    bidder_state_data.nonce += 1;
+    bidder_state_data.serialize(&mut &mut bidder_state.data.borrow_mut()[..])?;
+
+    bid_commit_data.revealed = true;
+    bid_commit_data.amount = bid_amount;
+    bid_commit_data.serialize(&mut &mut bid_commit.data.borrow_mut()[..])?;
+
+    sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
+
+    VaultEvent::BidAdded {
+        subscription: *sub_state.key,
+        bidder: *bidder.key,
+        amount: bid_amount,
+        leader: sub_state_data.executor,
+        rent: sub_state_data.rent,
+    }
+    .emit();
+    Ok(())
+}
 
-pub fn claimbid(
+pub fn commit_bid(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _signature: [u8; 64],
+    commitment: [u8; 32],
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    let consensus: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let bidder = next_account_info(account_info_iter)?;
+    let bidder_ata = next_account_info(account_info_iter)?;
+    let bidder_operator = next_account_info(account_info_iter)?;
+    let bid_commit = next_account_info(account_info_iter)?;
+    let sub_state = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !bidder.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(bidder.key, bidder_ata) {
+        msg!("Wrong associated token account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if sub_state.owner != program_id {
+        msg!("Wrong sub state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let bidder_operator_data = is_eligible_operator(
+        OPERATOR_STATE,
+        MEASUREMENT_REGISTRY,
+        bidder.key,
+        bidder_operator,
+        measurement_registry,
+        program_id,
+    )?;
+
+    if is_attestation_lapsed(bidder_operator_data.attestation_expiry)? {
+        msg!("Operator attestation lapsed, re-attest before bidding");
+        return Err(VaultError::AttestationExpired.into());
+    }
+
+    let sub_state_data =
+        try_from_slice_unchecked::<VaultUserSubscriptionState>(&sub_state.data.borrow()).unwrap();
+
+    if !sub_state_data.is_initialized() {
+        msg!("subsciption not init yet!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if sub_state_data.closed {
+        msg!("subscription already closed!");
+        return Err(VaultError::SubScriptionClosed.into());
+    }
+
+    if sub_state_data.auction_mode != AuctionMode::SealedBid {
+        msg!("Subscription doesn't run a sealed bid auction");
+        return Err(VaultError::InvalidAuctionMode.into());
+    }
+
+    let clock = Clock::get()?;
+    if sub_state_data.is_assigned || clock.unix_timestamp as u64 >= sub_state_data.reveal_time {
+        msg!("Commit phase is over");
+        return Err(VaultError::CommitPhaseOver.into());
+    }
+
+    let (bid_commit_pda, _bump_seed) = Pubkey::find_program_address(
+        &[
+            BID_COMMIT.as_bytes(),
+            sub_state.key.as_ref(),
+            bidder.key.as_ref(),
+        ],
+        program_id,
+    );
+
+    if bid_commit_pda != *bid_commit.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    // a commit left over from an earlier round has to be settled before committing again
+    let state_size = 1 + 32 + 32 + 8 + 32 + 8 + 1 + 8 + 1;
+    let rent = Rent::get()?;
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            bidder.key,
+            bid_commit.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[bidder.clone(), bid_commit.clone(), system_program.clone()],
+        &[&[
+            BID_COMMIT.as_bytes(),
+            sub_state.key.as_ref(),
+            bidder.key.as_ref(),
+            &[_bump_seed],
+        ]],
+    )?;
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+    let bond = protocol_config_data.params.bid_bond;
+
+    if bond > 0 {
+        let transfer_bond_to_programm = transfer(
+            &TOKEN_PROGRAM_ID,
+            bidder_ata.key,
+            program_ata.key,
+            bidder.key,
+            &[],
+            bond,
+        )?;
+
+        invoke(
+            &transfer_bond_to_programm,
+            &[
+                bidder_ata.clone(),
+                program_ata.clone(),
+                bidder.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    }
+
+    let bid_commit_data = VaultBidCommitState {
+        is_initialized: true,
+        subscription: *sub_state.key,
+        bidder: *bidder.key,
+        round: sub_state_data.auction_round,
+        commitment,
+        bond,
+        revealed: false,
+        amount: 0,
+        forfeited: false,
+    };
+    bid_commit_data.serialize(&mut &mut bid_commit.data.borrow_mut()[..])?;
+
+    VaultEvent::BidCommitted {
+        subscription: *sub_state.key,
+        bidder: *bidder.key,
+        round: sub_state_data.auction_round,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn settle_bid(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let bidder = next_account_info(account_info_iter)?;
+    let bidder_ata = next_account_info(account_info_iter)?;
+    let bid_commit = next_account_info(account_info_iter)?;
+    let sub_state = next_account_info(account_info_iter)?;
+    let app_state = next_account_info(account_info_iter)?;
+    let sub_bids = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let user_state = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !is_ata_owner(bidder.key, bidder_ata) {
+        msg!("Wrong associated token account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if sub_state.owner != program_id {
+        msg!("Wrong sub state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let program_treasury_bump_seed =
+        check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let bid_commit_data = load_bid_commit(program_id, bid_commit, sub_state.key, bidder.key)?;
+
+    let sub_state_data =
+        try_from_slice_unchecked::<VaultUserSubscriptionState>(&sub_state.data.borrow()).unwrap();
+
+    let (sub_state_pda, _) = Pubkey::find_program_address(
+        &[
+            SUB_STATE.as_bytes(),
+            user.key.as_ref(),
+            sub_state_data.id.to_be_bytes().as_ref(),
+        ],
+        program_id,
+    );
+
+    if sub_state_pda != *sub_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let app_state_data = load_app(program_id, app_state, sub_state_data.app_id)?;
+
+    let clock = Clock::get()?;
+    let cur_time = clock.unix_timestamp as u64;
+    let current_round = bid_commit_data.round == sub_state_data.auction_round;
+
+    if current_round && cur_time < sub_state_data.bid_endtime {
+        msg!("Bonds are only settled once the auction ends");
+        return Err(VaultError::AuctionNotSettled.into());
+    }
+
+    let rank = if current_round && bid_commit_data.revealed {
+        let sub_bids_data = load_sub_bids(program_id, sub_bids, sub_state.key)?;
+        sub_bids_data
+            .bids
+            .iter()
+            .position(|ranked_bid| ranked_bid.bidder == *bidder.key)
+    } else {
+        None
+    };
+
+    let defaulted = match rank {
+        Some(rank) if sub_state_data.is_assigned => rank < sub_state_data.winner_rank as usize,
+        Some(rank) => {
+            let claim_window_end = (rank as u64 + 1)
+                .checked_mul(app_state_data.schedule.claim_window)
+                .and_then(|claim_windows| sub_state_data.bid_endtime.checked_add(claim_windows))
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            if cur_time <= claim_window_end {
+                msg!("Ranked bond stays locked until its claim window passes");
+                return Err(VaultError::AuctionNotSettled.into());
+            }
+            true
+        }
+        None => false,
+    };
+
+    // bidders that never revealed and ranked bidders that let their claim window lapse lose their bond to the subscriber
+    let forfeit = !bid_commit_data.revealed || defaulted;
+
+    if bid_commit_data.forfeited {
+        msg!("Bond already forfeited on re-assignment");
+    } else if forfeit {
+        let mut user_state_data =
+            try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+        user_state_data.balance += bid_commit_data.bond;
+        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+    } else if bid_commit_data.bond > 0 {
+        let transfer_bond_to_bidder = transfer(
+            &TOKEN_PROGRAM_ID,
+            program_ata.key,
+            bidder_ata.key,
+            program_treasury.key,
+            &[],
+            bid_commit_data.bond,
+        )?;
+
+        invoke_signed(
+            &transfer_bond_to_bidder,
+            &[
+                program_ata.clone(),
+                bidder_ata.clone(),
+                program_treasury.clone(),
+                token_program.clone(),
+            ],
+            &[&[TREASURY_STATE.as_bytes(), &[program_treasury_bump_seed]]],
+        )?;
+    }
+
+    // close the commit so the bidder gets its rent back and can commit in a later round
+    let commit_lamports = bid_commit.lamports();
+    **bid_commit.try_borrow_mut_lamports()? = 0;
+    **bidder.try_borrow_mut_lamports()? += commit_lamports;
+    bid_commit.data.borrow_mut().fill(0);
+
+    VaultEvent::BidSettled {
+        subscription: *sub_state.key,
+        bidder: *bidder.key,
+        bond: bid_commit_data.bond,
+        forfeited: forfeit || bid_commit_data.forfeited,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn claimbid(program_id: &Pubkey, accounts: &[AccountInfo], expiry_slot: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
     let bid_winner = next_account_info(account_info_iter)?;
     let bid_winner_state = next_account_info(account_info_iter)?;
+    let bid_winner_operator = next_account_info(account_info_iter)?;
     let sub_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let app_state = next_account_info(account_info_iter)?;
     let metadata: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
     let ix_sysvar: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let sub_bids = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let user_state = next_account_info(account_info_iter)?;
 
     if !bid_winner.is_signer {
         msg!("Missing required signature");
@@ -702,6 +1704,20 @@ pub fn claimbid(
         return Err(VaultError::InvalidPDA.into());
     }
 
+    let bid_winner_operator_data = is_eligible_operator(
+        OPERATOR_STATE,
+        MEASUREMENT_REGISTRY,
+        bid_winner.key,
+        bid_winner_operator,
+        measurement_registry,
+        program_id,
+    )?;
+
+    if is_attestation_lapsed(bid_winner_operator_data.attestation_expiry)? {
+        msg!("Operator attestation lapsed, re-attest before claiming");
+        return Err(VaultError::AttestationExpired.into());
+    }
+
     let mut bid_winner_state_data =
         try_from_slice_unchecked::<VaultBidderState>(&bid_winner_state.data.borrow()).unwrap();
 
@@ -710,18 +1726,25 @@ pub fn claimbid(
         return Err(ProgramError::UninitializedAccount);
     }
 
-    let mut raw_message: [u8; 40] = [0; 40];
-    raw_message[..32].copy_from_slice(bid_winner.key.to_bytes().as_ref());
-    raw_message[32..].copy_from_slice(bid_winner_state_data.nonce.to_be_bytes().as_ref());
+    let message = ConsensusMessage {
+        version: CONSENSUS_MSG_VERSION,
+        action: ConsensusAction::ClaimBid,
+        program_id: *program_id,
+        subscription: *sub_state.key,
+        operator: *bid_winner.key,
+        operator_nonce: bid_winner_state_data.nonce,
+        value: 0,
+        expiry_slot,
+    };
 
     is_valid_consesues(
         VAULT_METADATA,
+        MEASUREMENT_REGISTRY,
         ix_sysvar,
-        consensus,
         metadata,
+        measurement_registry,
         program_id,
-        raw_message.as_ref(),
-        _signature.as_ref(),
+        &message,
     )?;
 
     let mut sub_state_data =
@@ -742,22 +1765,102 @@ pub fn claimbid(
         return Err(VaultError::BidAlreadyClaimed.into());
     }
 
-    if sub_state_data.executor != *bid_winner.key {
-        msg!("You do not won the bid!");
-        return Err(VaultError::UnAuthToClaimBid.into());
-    }
+    let sub_bids_data = load_sub_bids(program_id, sub_bids, sub_state.key)?;
+
+    let rank = match sub_bids_data
+        .bids
+        .iter()
+        .position(|ranked_bid| ranked_bid.bidder == *bid_winner.key)
+    {
+        Some(rank) if sub_bids_data.round == sub_state_data.auction_round => rank,
+        _ => {
+            msg!("You do not won the bid!");
+            return Err(VaultError::UnAuthToClaimBid.into());
+        }
+    };
+
+    let app_state_data = load_app(program_id, app_state, sub_state_data.app_id)?;
+    let claim_window = app_state_data.schedule.claim_window;
+
+    // every ranked bidder gets its own claim window once everyone ranked above let theirs lapse
+    let window_start = (rank as u64)
+        .checked_mul(claim_window)
+        .and_then(|claim_windows| sub_state_data.bid_endtime.checked_add(claim_windows))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let window_end = window_start
+        .checked_add(claim_window)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
 
     let clock = Clock::get()?;
     let cur_time = clock.unix_timestamp as u64;
-    if cur_time > sub_state_data.bid_endtime + 300 {
+    if cur_time > window_end {
         msg!("You failed to claim bid!");
-        //TODO: add logic to decrease the reputation of bid winner
+        // reputation and bid bond are taken once a lower ranked bidder claims or a keeper reassigns the subscription
         return Err(VaultError::BidClaimExpired.into());
     } else if cur_time < sub_state_data.bid_endtime {
         msg!("Trying to claim bid too early!");
-        return  Err(VaultError::ReportedEarly.into());
+        return Err(VaultError::ReportedEarly.into());
+    } else if cur_time < window_start {
+        msg!("Higher ranked bidder can still claim!");
+        return Err(VaultError::ClaimWindowNotOpen.into());
+    }
+
+    if rank > 0 {
+        if user_state.owner != program_id {
+            msg!("Wrong user state account provided");
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let (sub_state_pda, _) = Pubkey::find_program_address(
+            &[
+                SUB_STATE.as_bytes(),
+                user.key.as_ref(),
+                sub_state_data.id.to_be_bytes().as_ref(),
+            ],
+            program_id,
+        );
+
+        if sub_state_pda != *sub_state.key {
+            msg!("Invalid seeds for PDA");
+            return Err(VaultError::InvalidPDA.into());
+        }
+
+        let (user_state_pda, _) =
+            Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+        if user_state_pda != *user_state.key {
+            msg!("Invalid seeds for PDA");
+            return Err(VaultError::InvalidPDA.into());
+        }
+
+        let mut user_state_data =
+            try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+
+        // commit and reputation of every skipped bidder follow as remaining accounts, in rank order
+        for skipped_bid in &sub_bids_data.bids[..rank] {
+            let skipped_commit = next_account_info(account_info_iter)?;
+            let skipped_reputation = next_account_info(account_info_iter)?;
+            forfeit_bid_bond(
+                program_id,
+                skipped_commit,
+                sub_state.key,
+                &skipped_bid.bidder,
+                sub_state_data.auction_round,
+                &mut user_state_data,
+            )?;
+            update_reputation(program_id, skipped_reputation, &skipped_bid.bidder, false)?;
+        }
+
+        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
     }
 
+    // runner-up takes the job at its own price
+    let claimed_bid = &sub_bids_data.bids[rank];
+    sub_state_data.executor = claimed_bid.bidder;
+    sub_state_data.rent = claimed_bid.amount;
+    sub_state_data.bid_score = claimed_bid.score;
+    sub_state_data.winner_rank = rank as u8;
+
     bid_winner_state_data.nonce += 1;
     bid_winner_state_data.serialize(&mut &mut bid_winner_state.data.borrow_mut()[..])?;
 
@@ -765,6 +1868,14 @@ pub fn claimbid(
     sub_state_data.last_report_time = cur_time;
     sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
 
+    VaultEvent::BidClaimed {
+        subscription: *sub_state.key,
+        executor: sub_state_data.executor,
+        rent: sub_state_data.rent,
+        rank: sub_state_data.winner_rank,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -772,20 +1883,26 @@ pub fn report_work(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     nonce: u64,
-    _signature: [u8; 64],
+    expiry_slot: u64,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
 
-    let consensus = next_account_info(account_info_iter)?;
     let bid_winner = next_account_info(account_info_iter)?;
     let bid_winner_state = next_account_info(account_info_iter)?;
+    let bid_winner_operator = next_account_info(account_info_iter)?;
+    let bid_winner_reputation = next_account_info(account_info_iter)?;
     let bid_winner_ata = next_account_info(account_info_iter)?;
     let sub_state = next_account_info(account_info_iter)?;
+    let sub_escrow = next_account_info(account_info_iter)?;
+    let app_state = next_account_info(account_info_iter)?;
+    let creator_ata = next_account_info(account_info_iter)?;
     let user = next_account_info(account_info_iter)?;
     let user_state = next_account_info(account_info_iter)?;
     let program_treasury = next_account_info(account_info_iter)?;
     let program_ata = next_account_info(account_info_iter)?;
     let metadata = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
     let ix_sysvar = next_account_info(account_info_iter)?;
 
@@ -866,18 +1983,43 @@ pub fn report_work(
         return Err(ProgramError::UninitializedAccount);
     }
 
-    let mut raw_message: [u8; 40] = [0; 40];
-    raw_message[..32].copy_from_slice(bid_winner.key.to_bytes().as_ref());
-    raw_message[32..].copy_from_slice(bid_winner_state_data.nonce.to_be_bytes().as_ref());
+    is_staked_operator(
+        OPERATOR_STATE,
+        bid_winner.key,
+        bid_winner_operator,
+        program_id,
+    )?;
+
+    let bid_winner_operator_data =
+        try_from_slice_unchecked::<VaultOperatorState>(&bid_winner_operator.data.borrow()).unwrap();
+
+    // a revoked measurement restarts the subscription below instead of failing the report
+    let measurement_revoked = is_revoked_measurement(
+        MEASUREMENT_REGISTRY,
+        measurement_registry,
+        &bid_winner_operator_data.measurement,
+        program_id,
+    )?;
+
+    let message = ConsensusMessage {
+        version: CONSENSUS_MSG_VERSION,
+        action: ConsensusAction::ReportWork,
+        program_id: *program_id,
+        subscription: *sub_state.key,
+        operator: *bid_winner.key,
+        operator_nonce: bid_winner_state_data.nonce,
+        value: nonce,
+        expiry_slot,
+    };
 
     is_valid_consesues(
         VAULT_METADATA,
+        MEASUREMENT_REGISTRY,
         ix_sysvar,
-        consensus,
         metadata,
+        measurement_registry,
         program_id,
-        raw_message.as_ref(),
-        _signature.as_ref(),
+        &message,
     )?;
 
     let mut sub_state_data =
@@ -922,14 +2064,35 @@ pub fn report_work(
         return Err(VaultError::RestartPhase.into());
     }
 
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+    let app_state_data = load_app(program_id, app_state, sub_state_data.app_id)?;
+    let schedule = &app_state_data.schedule;
+
+    if app_state_data.creator_ata != *creator_ata.key {
+        msg!("Wrong app creator ata provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let report_due = sub_state_data
+        .last_report_time
+        .checked_add(schedule.report_interval)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let report_deadline = report_due
+        .checked_add(schedule.grace_period)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
     let clock = Clock::get()?;
     let cur_time = clock.unix_timestamp as u64;
-    if cur_time > sub_state_data.last_report_time + 900 {
-        msg!("worker/bid winner failed to provide SLA!");
-        // TODO: add logic to decrease reputation
-        // use this restart flag in future where bots report the worker and help protocol to restart the particular subscription/ work and get some reword for this good work by slashing it from the locked dpeosits of workers
+    if is_attestation_lapsed(bid_winner_operator_data.attestation_expiry)? {
+        msg!("worker/bid winner attestation lapsed!");
         sub_state_data.restart = true;
-    } else if cur_time < sub_state_data.last_report_time + 600 {
+    } else if measurement_revoked {
+        msg!("worker/bid winner enclave measurement revoked!");
+        sub_state_data.restart = true;
+    } else if cur_time > report_deadline {
+        msg!("worker/bid winner failed to provide SLA!");
+        sub_state_data.restart = true;
+    } else if cur_time < report_due {
         msg!("reported too early!");
         return Err(VaultError::ReportedEarly.into());
     }
@@ -939,18 +2102,85 @@ pub fn report_work(
         sub_state_data.restart = true;
     }
 
-    if !user_state_data.balance < sub_state_data.rent {
+    if sub_state_data.restart {
+        // executor surfaced its own fault, so there is no third-party reporter to reward
+        let (subscriber_amount, _) = slash_executor(
+            program_id,
+            bid_winner_operator,
+            bid_winner.key,
+            sub_state.key,
+            None,
+            &protocol_config_data.params,
+        )?;
+
+        user_state_data.balance += subscriber_amount;
+        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+
+        VaultEvent::SubRestarted {
+            subscription: *sub_state.key,
+            executor: *bid_winner.key,
+            reporter: None,
+        }
+        .emit();
+    }
+
+    update_reputation(
+        program_id,
+        bid_winner_reputation,
+        bid_winner.key,
+        !sub_state_data.restart,
+    )?;
+
+    let mut sub_escrow_data = if sub_state_data.has_escrow {
+        Some(load_escrow(program_id, sub_escrow, sub_state.key)?)
+    } else {
+        None
+    };
+
+    let available_balance = match &sub_escrow_data {
+        Some(escrow) => escrow.balance,
+        None => user_state_data.balance,
+    };
+
+    let cycle_rent = sub_state_data
+        .rent
+        .checked_add(sub_state_data.creator_rent)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    if available_balance < cycle_rent {
         sub_state_data.closed = true;
+        if !sub_state_data.has_escrow {
+            user_state_data.reserved = user_state_data.reserved.saturating_sub(
+                sub_state_data
+                    .max_rent
+                    .saturating_add(sub_state_data.creator_rent),
+            );
+            user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+        }
         msg!("Insufficient balance");
-        msg!("SubClosed:{}", sub_state.key);
+        VaultEvent::SubClosed {
+            subscription: *sub_state.key,
+        }
+        .emit();
     } else if !sub_state_data.restart {
+        // protocol fee stays in the treasury ata and is tracked apart from user deposits
+        let protocol_fee = (sub_state_data.rent as u128
+            * protocol_config_data.params.protocol_fee_bps as u128
+            / MAX_BPS as u128) as u64;
+
+        let mut program_treasury_data =
+            try_from_slice_unchecked::<VaultTreasuryState>(&program_treasury.data.borrow())
+                .unwrap();
+        program_treasury_data.accrued_fees += protocol_fee;
+        program_treasury_data.serialize(&mut &mut program_treasury.data.borrow_mut()[..])?;
+
         let transfer_token_to_worker = transfer(
             &TOKEN_PROGRAM_ID,
             program_ata.key,
             bid_winner_ata.key,
             program_treasury.key,
             &[],
-            sub_state_data.rent,
+            sub_state_data.rent - protocol_fee,
         )?;
 
         invoke_signed(
@@ -964,10 +2194,50 @@ pub fn report_work(
             &[&[TREASURY_STATE.as_bytes(), &[_pata_bump_seed]]],
         )?;
 
+        if sub_state_data.creator_rent > 0 {
+            let transfer_token_to_creator = transfer(
+                &TOKEN_PROGRAM_ID,
+                program_ata.key,
+                creator_ata.key,
+                program_treasury.key,
+                &[],
+                sub_state_data.creator_rent,
+            )?;
+
+            invoke_signed(
+                &transfer_token_to_creator,
+                &[
+                    program_ata.clone(),
+                    creator_ata.clone(),
+                    program_treasury.clone(),
+                    token_program.clone(),
+                ],
+                &[&[TREASURY_STATE.as_bytes(), &[_pata_bump_seed]]],
+            )?;
+        }
+
         sub_state_data.nonce += 1;
         sub_state_data.last_report_time = cur_time;
-        user_state_data.balance -= sub_state_data.rent;
-        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+        match sub_escrow_data.as_mut() {
+            Some(escrow) => {
+                escrow.balance -= cycle_rent;
+                escrow.serialize(&mut &mut sub_escrow.data.borrow_mut()[..])?;
+            }
+            None => {
+                user_state_data.balance -= cycle_rent;
+                user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+            }
+        }
+
+        VaultEvent::WorkReported {
+            subscription: *sub_state.key,
+            executor: *bid_winner.key,
+            nonce,
+            executor_amount: sub_state_data.rent - protocol_fee,
+            creator_amount: sub_state_data.creator_rent,
+            protocol_fee,
+        }
+        .emit();
     }
 
     bid_winner_state_data.nonce += 1;
@@ -981,6 +2251,8 @@ pub fn close_sub(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult
     let account_info_iter = &mut accounts.iter();
     let user: &AccountInfo<'_> = next_account_info(account_info_iter)?;
     let user_sub: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_sub_escrow: &AccountInfo<'_> = next_account_info(account_info_iter)?;
 
     if !user.is_signer {
         msg!("Missing required signature");
@@ -992,6 +2264,19 @@ pub fn close_sub(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult
         return Err(ProgramError::InvalidAccountOwner);
     }
 
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
     let mut sub_state_data =
         try_from_slice_unchecked::<VaultUserSubscriptionState>(&user_sub.data.borrow()).unwrap();
 
@@ -1014,11 +2299,1669 @@ pub fn close_sub(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult
         return Err(VaultError::InvalidPDA.into());
     }
 
+    let mut user_state_data =
+        try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+
+    if sub_state_data.has_escrow {
+        // whatever is left in the escrow goes back to the pooled balance so it can be withdrawn
+        let mut user_sub_escrow_data = load_escrow(program_id, user_sub_escrow, user_sub.key)?;
+        user_state_data.balance += user_sub_escrow_data.balance;
+        user_sub_escrow_data.balance = 0;
+        user_sub_escrow_data.serialize(&mut &mut user_sub_escrow.data.borrow_mut()[..])?;
+    } else if !sub_state_data.closed {
+        // subscriptions closed by report_work already gave their reserve back
+        user_state_data.reserved = user_state_data.reserved.saturating_sub(
+            sub_state_data
+                .max_rent
+                .saturating_add(sub_state_data.creator_rent),
+        );
+    }
+    user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+
     sub_state_data.closed = true;
     sub_state_data.serialize(&mut &mut user_sub.data.borrow_mut()[..])?;
-    msg!("SubClosed:{}", user_sub.key);
+    VaultEvent::SubClosed {
+        subscription: *user_sub.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn topup_sub(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let user: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_ata: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_sub: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_sub_escrow: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !user.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(user.key, user_ata) {
+        msg!("Wrong ata provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if amount == 0 || amount < protocol_config_data.params.min_topup {
+        msg!("Amount can't be less then minimum topup");
+        return Err(VaultError::LessThenMinimumTopupAmount.into());
+    }
+
+    let user_ata_data = Account::unpack(&user_ata.data.borrow())?;
+    if user_ata_data.amount < amount {
+        msg!("Insufficient funds to topup!");
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if user_sub.owner != program_id {
+        msg!("Wrong sub state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let mut sub_state_data =
+        try_from_slice_unchecked::<VaultUserSubscriptionState>(&user_sub.data.borrow()).unwrap();
+
+    if !sub_state_data.is_initialized() {
+        msg!("Invalid user sub state details provided!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let (user_sub_pda, _) = Pubkey::find_program_address(
+        &[
+            SUB_STATE.as_bytes(),
+            user.key.as_ref(),
+            sub_state_data.id.to_be_bytes().as_ref(),
+        ],
+        program_id,
+    );
+
+    if user_sub_pda != *user_sub.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if sub_state_data.closed {
+        msg!("subscription already closed!");
+        return Err(VaultError::SubScriptionClosed.into());
+    }
+
+    // the first top-up moves the subscription off the pooled balance, so it has to fund a full cycle
+    if !sub_state_data.has_escrow {
+        let cycle_rent = sub_state_data
+            .max_rent
+            .checked_add(sub_state_data.creator_rent)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        if amount < cycle_rent {
+            msg!("escrow should cover rent for atleast one cycle");
+            return Err(VaultError::InefficientBalance.into());
+        }
+    }
+
+    let mut user_sub_escrow_data = if sub_state_data.has_escrow {
+        load_escrow(program_id, user_sub_escrow, user_sub.key)?
+    } else {
+        create_escrow(program_id, user, user_sub, user_sub_escrow, system_program)?
+    };
+
+    let transfer_tokens_to_programm = transfer(
+        &TOKEN_PROGRAM_ID,
+        user_ata.key,
+        program_ata.key,
+        user.key,
+        &[],
+        amount,
+    )?;
+
+    invoke(
+        &transfer_tokens_to_programm,
+        &[
+            user_ata.clone(),
+            program_ata.clone(),
+            user.clone(),
+            token_program.clone(),
+        ],
+    )?;
+
+    if !sub_state_data.has_escrow {
+        // subscription now pays from its own escrow, so its rent no longer needs to be reserved
+        let mut user_state_data =
+            try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+        user_state_data.reserved = user_state_data.reserved.saturating_sub(
+            sub_state_data
+                .max_rent
+                .saturating_add(sub_state_data.creator_rent),
+        );
+        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+
+        sub_state_data.has_escrow = true;
+        sub_state_data.serialize(&mut &mut user_sub.data.borrow_mut()[..])?;
+    }
+
+    user_sub_escrow_data.balance += amount;
+    user_sub_escrow_data.serialize(&mut &mut user_sub_escrow.data.borrow_mut()[..])?;
+
+    VaultEvent::SubToppedUp {
+        subscription: *user_sub.key,
+        amount,
+        escrow_balance: user_sub_escrow_data.balance,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn rotate_consensus(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    next_signers: Vec<Pubkey>,
+    next_threshold: u8,
+    activation_epoch: u64,
+    expiry_slot: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let metadata = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let ix_sysvar = next_account_info(account_info_iter)?;
+
+    // the signer set itself doesn't fit the message so it is bound through its hash
+    let next_set_hash = hashv(&[&borsh::to_vec(&(&next_signers, next_threshold))?]);
+
+    let message = ConsensusMessage {
+        version: CONSENSUS_MSG_VERSION,
+        action: ConsensusAction::RotateConsensus,
+        program_id: *program_id,
+        subscription: Pubkey::new_from_array(next_set_hash.to_bytes()),
+        operator: Pubkey::default(),
+        operator_nonce: 0,
+        value: activation_epoch,
+        expiry_slot,
+    };
+
+    let approvals = is_valid_consesues(
+        VAULT_METADATA,
+        MEASUREMENT_REGISTRY,
+        ix_sysvar,
+        metadata,
+        measurement_registry,
+        program_id,
+        &message,
+    )?;
+
+    check_signer_set(&next_signers, next_threshold)?;
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    let mut metadata_data =
+        try_from_slice_unchecked::<VaultMetaDataState>(&metadata.data.borrow()).unwrap();
+
+    let epoch = Clock::get()?.epoch;
+
+    // once the pending set is live only it may rotate, otherwise the outgoing set could hijack the handover
+    if !metadata_data.next_signers.is_empty() && epoch >= metadata_data.rotation_epoch {
+        if !has_quorum(
+            &metadata_data.next_signers,
+            metadata_data.next_threshold,
+            &approvals,
+        ) {
+            msg!("Only the incoming consesues set can rotate now");
+            return Err(VaultError::InvalidKeyRotation.into());
+        }
+        metadata_data.signers = metadata_data.next_signers.clone();
+        metadata_data.threshold = metadata_data.next_threshold;
+    } else if !has_quorum(&metadata_data.signers, metadata_data.threshold, &approvals) {
+        msg!("Only the current consesues set can rotate");
+        return Err(VaultError::InvalidKeyRotation.into());
+    }
+
+    if activation_epoch < epoch {
+        msg!("Activation epoch already passed");
+        return Err(VaultError::InvalidKeyRotation.into());
+    }
+
+    metadata_data.next_signers = next_signers;
+    metadata_data.next_threshold = next_threshold;
+    metadata_data.rotation_epoch = activation_epoch;
+    metadata_data.grace_end_epoch =
+        activation_epoch + protocol_config_data.params.key_rotation_grace;
+    metadata_data.serialize(&mut &mut metadata.data.borrow_mut()[..])?;
+
+    VaultEvent::ConsensusRotated {
+        next_signers: metadata_data.next_signers.clone(),
+        next_threshold: metadata_data.next_threshold,
+        rotation_epoch: metadata_data.rotation_epoch,
+        grace_end_epoch: metadata_data.grace_end_epoch,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn set_measurement(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    measurement: [u8; 32],
+    approved: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+
+    if !admin.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if protocol_config_data.admin != *admin.key {
+        msg!("Only protocol admin can manage measurements");
+        return Err(VaultError::UnAuthAdmin.into());
+    }
+
+    if measurement_registry.owner != program_id {
+        msg!("Wrong measurement registry account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (measurement_registry_pda, _) =
+        Pubkey::find_program_address(&[MEASUREMENT_REGISTRY.as_bytes()], program_id);
+
+    if measurement_registry_pda != *measurement_registry.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let mut measurement_registry_data = try_from_slice_unchecked::<VaultMeasurementRegistryState>(
+        &measurement_registry.data.borrow(),
+    )
+    .unwrap();
+
+    if !measurement_registry_data.is_initialized() {
+        msg!("Measurement registry not initialized!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // revoking only drops the entry, operators and consensus pinned to it fail the allowlist check from now on
+    if approved {
+        if !measurement_registry_data
+            .measurements
+            .contains(&measurement)
+        {
+            if measurement_registry_data.measurements.len() >= MAX_MEASUREMENTS {
+                msg!("Measurement registry is full");
+                return Err(VaultError::MeasurementRegistryFull.into());
+            }
+            measurement_registry_data.measurements.push(measurement);
+        }
+    } else {
+        measurement_registry_data
+            .measurements
+            .retain(|approved_measurement| *approved_measurement != measurement);
+    }
+
+    measurement_registry_data.serialize(&mut &mut measurement_registry.data.borrow_mut()[..])?;
+
+    VaultEvent::MeasurementUpdated {
+        measurement,
+        approved,
+    }
+    .emit();
 
     Ok(())
 }
 
-// TODO: add function by which bots can help to re-assign restarted subscription. And reset their state like nonce, executor and etc. For this they will also get reward which will be slashed from bad worker's locked deposit
+pub fn re_attest(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    attestation_proof: String,
+    measurement: [u8; 32],
+    expiry_slot: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let operator = next_account_info(account_info_iter)?;
+    let operator_state = next_account_info(account_info_iter)?;
+    let metadata = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let ix_sysvar = next_account_info(account_info_iter)?;
+
+    if !operator.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if attestation_proof.len() > protocol_config_data.params.max_attestation_len as usize {
+        msg!("Attestation proof too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
+    is_approved_measurement(
+        MEASUREMENT_REGISTRY,
+        measurement_registry,
+        &measurement,
+        program_id,
+    )?;
+
+    let mut operator_state_data = load_operator(program_id, operator_state, operator.key)?;
+
+    // the consensus verifies the new quote off-chain, binding the expiry it replaces makes the
+    // approval single use
+    let message = ConsensusMessage {
+        version: CONSENSUS_MSG_VERSION,
+        action: ConsensusAction::ReAttest,
+        program_id: *program_id,
+        subscription: attestation_commitment(&attestation_proof, &measurement),
+        operator: *operator.key,
+        operator_nonce: 0,
+        value: operator_state_data.attestation_expiry,
+        expiry_slot,
+    };
+
+    is_valid_consesues(
+        VAULT_METADATA,
+        MEASUREMENT_REGISTRY,
+        ix_sysvar,
+        metadata,
+        measurement_registry,
+        program_id,
+        &message,
+    )?;
+
+    resize_account(
+        operator,
+        operator_state,
+        operator_size(attestation_proof.len()),
+        system_program,
+    )?;
+
+    let clock = Clock::get()?;
+    operator_state_data.attestation_proof = attestation_proof;
+    operator_state_data.measurement = measurement;
+    operator_state_data.attestation_expiry =
+        clock.unix_timestamp as u64 + protocol_config_data.params.attestation_ttl;
+    operator_state_data.serialize(&mut &mut operator_state.data.borrow_mut()[..])?;
+
+    VaultEvent::OperatorReAttested {
+        operator: *operator.key,
+        attestation_expiry: operator_state_data.attestation_expiry,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn re_attest_consensus(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    attestation_proof: String,
+    measurement: [u8; 32],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let metadata = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    if !admin.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    // consensus proofs are checked off-chain by governance, same as approving measurements
+    if protocol_config_data.admin != *admin.key {
+        msg!("Only protocol admin can re-attest consesues");
+        return Err(VaultError::UnAuthAdmin.into());
+    }
+
+    if attestation_proof.len() > protocol_config_data.params.max_attestation_len as usize {
+        msg!("Attestation proof too long");
+        return Err(VaultError::InputTooLong.into());
+    }
+
+    is_approved_measurement(
+        MEASUREMENT_REGISTRY,
+        measurement_registry,
+        &measurement,
+        program_id,
+    )?;
+
+    if metadata.owner != program_id {
+        msg!("Wrong metadata account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (metadata_pda, _) = Pubkey::find_program_address(&[VAULT_METADATA.as_bytes()], program_id);
+
+    if metadata_pda != *metadata.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let mut metadata_data =
+        try_from_slice_unchecked::<VaultMetaDataState>(&metadata.data.borrow()).unwrap();
+
+    if !metadata_data.is_initialized() {
+        msg!("Protocol not initialized!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    resize_account(
+        admin,
+        metadata,
+        metadata_size(attestation_proof.len()),
+        system_program,
+    )?;
+
+    let clock = Clock::get()?;
+    metadata_data.attestation_proof = attestation_proof;
+    metadata_data.measurement = measurement;
+    metadata_data.attestation_expiry =
+        clock.unix_timestamp as u64 + protocol_config_data.params.attestation_ttl;
+    metadata_data.serialize(&mut &mut metadata.data.borrow_mut()[..])?;
+
+    VaultEvent::ConsensusReAttested {
+        attestation_expiry: metadata_data.attestation_expiry,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn sweep_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let destination_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !admin.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    if protocol_config_data.admin != *admin.key {
+        msg!("Only protocol admin can sweep fees");
+        return Err(VaultError::UnAuthAdmin.into());
+    }
+
+    let program_treasury_bump_seed =
+        check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let mut program_treasury_data =
+        try_from_slice_unchecked::<VaultTreasuryState>(&program_treasury.data.borrow()).unwrap();
+
+    if !program_treasury_data.is_initialized() {
+        msg!("Treasury not initialized!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if amount == 0 || amount > program_treasury_data.accrued_fees {
+        msg!("Sweep amount exceeds accrued fees");
+        return Err(VaultError::InsufficientAccruedFees.into());
+    }
+
+    let transfer_fees_to_destination = transfer(
+        &TOKEN_PROGRAM_ID,
+        program_ata.key,
+        destination_ata.key,
+        program_treasury.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &transfer_fees_to_destination,
+        &[
+            program_ata.clone(),
+            destination_ata.clone(),
+            program_treasury.clone(),
+            token_program.clone(),
+        ],
+        &[&[TREASURY_STATE.as_bytes(), &[program_treasury_bump_seed]]],
+    )?;
+
+    program_treasury_data.accrued_fees -= amount;
+    program_treasury_data.swept_fees += amount;
+    program_treasury_data.serialize(&mut &mut program_treasury.data.borrow_mut()[..])?;
+
+    VaultEvent::FeesSwept {
+        destination: *destination_ata.key,
+        amount,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn withdraw(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let user: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_ata: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let user_state: &AccountInfo<'_> = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !user.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(user.key, user_ata) {
+        msg!("Wrong ata provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let program_treasury_bump_seed =
+        check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let mut user_state_data =
+        try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+
+    if !user_state_data.is_initialized() {
+        msg!("User not found!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    if amount == 0 {
+        msg!("Amount can't be zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // rent for the current cycle of every active subscription stays locked in the treasury
+    let available_balance = user_state_data
+        .balance
+        .saturating_sub(user_state_data.reserved);
+    if available_balance < amount {
+        msg!("Withdraw amount exceeds unreserved balance");
+        return Err(VaultError::InefficientBalance.into());
+    }
+
+    let transfer_tokens_to_user = transfer(
+        &TOKEN_PROGRAM_ID,
+        program_ata.key,
+        user_ata.key,
+        program_treasury.key,
+        &[],
+        amount,
+    )?;
+
+    invoke_signed(
+        &transfer_tokens_to_user,
+        &[
+            program_ata.clone(),
+            user_ata.clone(),
+            program_treasury.clone(),
+            token_program.clone(),
+        ],
+        &[&[TREASURY_STATE.as_bytes(), &[program_treasury_bump_seed]]],
+    )?;
+
+    user_state_data.balance -= amount;
+    user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+
+    VaultEvent::Withdrawn {
+        user: *user.key,
+        amount,
+        balance: user_state_data.balance,
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn report_fault(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let keeper = next_account_info(account_info_iter)?;
+    let keeper_ata = next_account_info(account_info_iter)?;
+    let sub_state = next_account_info(account_info_iter)?;
+    let app_state = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let user_state = next_account_info(account_info_iter)?;
+    let executor_operator = next_account_info(account_info_iter)?;
+    let executor_reputation = next_account_info(account_info_iter)?;
+    let measurement_registry = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    if !keeper.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(keeper.key, keeper_ata) {
+        msg!("Wrong associated token account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if sub_state.owner != program_id {
+        msg!("Wrong sub state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let program_treasury_bump_seed =
+        check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let mut user_state_data =
+        try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+
+    if !user_state_data.is_initialized() {
+        msg!("User not found!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let mut sub_state_data =
+        try_from_slice_unchecked::<VaultUserSubscriptionState>(&sub_state.data.borrow()).unwrap();
+
+    if !sub_state_data.is_initialized() {
+        msg!("Invalid subscription details provided!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let (sub_state_pda, _) = Pubkey::find_program_address(
+        &[
+            SUB_STATE.as_bytes(),
+            user.key.as_ref(),
+            sub_state_data.id.to_be_bytes().as_ref(),
+        ],
+        program_id,
+    );
+
+    if sub_state_pda != *sub_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if sub_state_data.closed {
+        msg!("subscription already closed!");
+        return Err(VaultError::SubScriptionClosed.into());
+    }
+
+    if !sub_state_data.is_assigned {
+        msg!("Bid not assigned yet!");
+        return Err(VaultError::BidAlreadyClaimed.into());
+    }
+
+    if sub_state_data.restart {
+        msg!("Subscription already in re-assign mode!");
+        return Err(VaultError::RestartPhase.into());
+    }
+
+    let clock = Clock::get()?;
+    let cur_time = clock.unix_timestamp as u64;
+    let app_state_data = load_app(program_id, app_state, sub_state_data.app_id)?;
+    let schedule = &app_state_data.schedule;
+
+    let executor_operator_data =
+        load_operator(program_id, executor_operator, &sub_state_data.executor)?;
+
+    let report_deadline = sub_state_data
+        .last_report_time
+        .checked_add(schedule.report_interval)
+        .and_then(|report_due| report_due.checked_add(schedule.grace_period))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // an executor running on a lapsed attestation or a revoked measurement is faulty even while
+    // its report is not yet due
+    if cur_time <= report_deadline
+        && !is_attestation_lapsed(executor_operator_data.attestation_expiry)?
+        && !is_revoked_measurement(
+            MEASUREMENT_REGISTRY,
+            measurement_registry,
+            &executor_operator_data.measurement,
+            program_id,
+        )?
+    {
+        msg!("Executor still has time to report!");
+        return Err(VaultError::ExecutorWithinSla.into());
+    }
+
+    let (subscriber_amount, reporter_amount) = slash_executor(
+        program_id,
+        executor_operator,
+        &sub_state_data.executor,
+        sub_state.key,
+        Some(keeper.key),
+        &protocol_config_data.params,
+    )?;
+
+    if reporter_amount > 0 {
+        let transfer_token_to_keeper = transfer(
+            &TOKEN_PROGRAM_ID,
+            program_ata.key,
+            keeper_ata.key,
+            program_treasury.key,
+            &[],
+            reporter_amount,
+        )?;
+
+        invoke_signed(
+            &transfer_token_to_keeper,
+            &[
+                program_ata.clone(),
+                keeper_ata.clone(),
+                program_treasury.clone(),
+                token_program.clone(),
+            ],
+            &[&[TREASURY_STATE.as_bytes(), &[program_treasury_bump_seed]]],
+        )?;
+    }
+
+    user_state_data.balance += subscriber_amount;
+    user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+
+    update_reputation(
+        program_id,
+        executor_reputation,
+        &sub_state_data.executor,
+        false,
+    )?;
+
+    sub_state_data.restart = true;
+    sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
+
+    VaultEvent::SubRestarted {
+        subscription: *sub_state.key,
+        executor: sub_state_data.executor,
+        reporter: Some(*keeper.key),
+    }
+    .emit();
+
+    Ok(())
+}
+
+pub fn reassign_sub(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let keeper = next_account_info(account_info_iter)?;
+    let keeper_ata = next_account_info(account_info_iter)?;
+    let sub_state = next_account_info(account_info_iter)?;
+    let app_state = next_account_info(account_info_iter)?;
+    let executor_operator = next_account_info(account_info_iter)?;
+    let program_treasury = next_account_info(account_info_iter)?;
+    let program_ata = next_account_info(account_info_iter)?;
+    let protocol_config = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let sub_bids = next_account_info(account_info_iter)?;
+    let user = next_account_info(account_info_iter)?;
+    let user_state = next_account_info(account_info_iter)?;
+
+    if !keeper.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !is_ata_owner(keeper.key, keeper_ata) {
+        msg!("Wrong associated token account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if sub_state.owner != program_id {
+        msg!("Wrong sub state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if user_state.owner != program_id {
+        msg!("Wrong user state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let program_treasury_bump_seed =
+        check_program_treasury(program_id, program_treasury, program_ata)?;
+
+    let protocol_config_data = load_config(program_id, protocol_config)?;
+
+    let mut sub_state_data =
+        try_from_slice_unchecked::<VaultUserSubscriptionState>(&sub_state.data.borrow()).unwrap();
+
+    if !sub_state_data.is_initialized() {
+        msg!("Invalid subscription details provided!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let (sub_state_pda, _) = Pubkey::find_program_address(
+        &[
+            SUB_STATE.as_bytes(),
+            user.key.as_ref(),
+            sub_state_data.id.to_be_bytes().as_ref(),
+        ],
+        program_id,
+    );
+
+    if sub_state_pda != *sub_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let (user_state_pda, _) =
+        Pubkey::find_program_address(&[USER_STATE.as_bytes(), user.key.as_ref()], program_id);
+
+    if user_state_pda != *user_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if sub_state_data.closed {
+        msg!("subscription already closed!");
+        return Err(VaultError::SubScriptionClosed.into());
+    }
+
+    let app_state_data = load_app(program_id, app_state, sub_state_data.app_id)?;
+
+    let clock = Clock::get()?;
+    let cur_time = clock.unix_timestamp as u64;
+
+    let sub_bids_data =
+        if !sub_state_data.is_assigned && sub_state_data.executor != Pubkey::default() {
+            Some(load_sub_bids(program_id, sub_bids, sub_state.key)?)
+        } else {
+            None
+        };
+
+    // claim windows of all ranked bidders are chained, the auction only fails once the last one lapsed
+    let claim_expired = match &sub_bids_data {
+        Some(sub_bids_data) => {
+            let last_claim_end = (sub_bids_data.bids.len() as u64)
+                .checked_mul(app_state_data.schedule.claim_window)
+                .and_then(|claim_windows| sub_state_data.bid_endtime.checked_add(claim_windows))
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            cur_time > last_claim_end
+        }
+        None => false,
+    };
+
+    // nobody bid or revealed a valid bid, so there is no executor to blame and nothing to claim
+    let auction_failed = !sub_state_data.is_assigned
+        && sub_state_data.executor == Pubkey::default()
+        && cur_time > sub_state_data.bid_endtime;
+
+    if !sub_state_data.restart && !claim_expired && !auction_failed {
+        msg!("Subscription is not waiting for re-assignment!");
+        return Err(VaultError::NotInRestartPhase.into());
+    }
+
+    // restarts already cost the executor reputation when they were flagged, a missed claim is only caught here
+    if claim_expired {
+        let mut user_state_data =
+            try_from_slice_unchecked::<VaultUserState>(&user_state.data.borrow()).unwrap();
+
+        // commit and reputation of every ranked bidder follow as remaining accounts, in rank order
+        for ranked_bid in sub_bids_data
+            .iter()
+            .flat_map(|sub_bids_data| &sub_bids_data.bids)
+        {
+            let ranked_commit = next_account_info(account_info_iter)?;
+            let ranked_reputation = next_account_info(account_info_iter)?;
+            forfeit_bid_bond(
+                program_id,
+                ranked_commit,
+                sub_state.key,
+                &ranked_bid.bidder,
+                sub_state_data.auction_round,
+                &mut user_state_data,
+            )?;
+            update_reputation(program_id, ranked_reputation, &ranked_bid.bidder, false)?;
+        }
+
+        user_state_data.serialize(&mut &mut user_state.data.borrow_mut()[..])?;
+    }
+
+    let mut bounty = protocol_config_data.params.keeper_bounty;
+    if auction_failed {
+        // nobody failed a job, so reopening the auction pays no bounty
+        bounty = 0;
+    } else {
+        let mut executor_operator_data =
+            load_operator(program_id, executor_operator, &sub_state_data.executor)?;
+
+        // bounty is slashed from the failed worker first and only falls back to accrued protocol fees once its stake is exhausted
+        if executor_operator_data.stake >= bounty {
+            executor_operator_data.stake -= bounty;
+            executor_operator_data.serialize(&mut &mut executor_operator.data.borrow_mut()[..])?;
+        } else {
+            let mut program_treasury_data =
+                try_from_slice_unchecked::<VaultTreasuryState>(&program_treasury.data.borrow())
+                    .unwrap();
+            if program_treasury_data.accrued_fees >= bounty {
+                program_treasury_data.accrued_fees -= bounty;
+                program_treasury_data
+                    .serialize(&mut &mut program_treasury.data.borrow_mut()[..])?;
+            } else {
+                msg!("No funds left to pay keeper bounty");
+                bounty = 0;
+            }
+        }
+    }
+
+    if bounty > 0 {
+        let transfer_bounty_to_keeper = transfer(
+            &TOKEN_PROGRAM_ID,
+            program_ata.key,
+            keeper_ata.key,
+            program_treasury.key,
+            &[],
+            bounty,
+        )?;
+
+        invoke_signed(
+            &transfer_bounty_to_keeper,
+            &[
+                program_ata.clone(),
+                keeper_ata.clone(),
+                program_treasury.clone(),
+                token_program.clone(),
+            ],
+            &[&[TREASURY_STATE.as_bytes(), &[program_treasury_bump_seed]]],
+        )?;
+    }
+
+    sub_state_data.executor = Pubkey::default();
+    sub_state_data.is_assigned = false;
+    sub_state_data.nonce = 0;
+    sub_state_data.rent = sub_state_data.max_rent;
+    sub_state_data.restart = false;
+    sub_state_data.bid_score = 0;
+    sub_state_data.auction_round += 1;
+    open_auction(&mut sub_state_data, &app_state_data.schedule, cur_time)?;
+    sub_state_data.serialize(&mut &mut sub_state.data.borrow_mut()[..])?;
+
+    VaultEvent::SubReassigned {
+        subscription: *sub_state.key,
+        round: sub_state_data.auction_round,
+        max_rent: sub_state_data.max_rent,
+        bid_endtime: sub_state_data.bid_endtime,
+        keeper_bounty: bounty,
+    }
+    .emit();
+
+    Ok(())
+}
+
+// room for a full signer set twice so rotations never need a realloc
+fn metadata_size(attestation_len: usize) -> usize {
+    1 + (4 + attestation_len) + 2 * (4 + 32 * MAX_CONSENSUS_SIGNERS + 1) + 8 + 8 + 32 + 8
+}
+
+fn operator_size(attestation_len: usize) -> usize {
+    1 + (4 + attestation_len) + 32 + 8 + 8 + 32 + 8
+}
+
+/// Grows an account for a longer attestation proof, payer tops up the rent difference.
+fn resize_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    new_size: usize,
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    if new_size <= account.data_len() {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let lamports_needed = rent
+        .minimum_balance(new_size)
+        .saturating_sub(account.lamports());
+
+    if lamports_needed > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, lamports_needed),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+
+    account.realloc(new_size, false)
+}
+
+fn open_auction(
+    sub_state_data: &mut VaultUserSubscriptionState,
+    schedule: &AppSchedule,
+    cur_time: u64,
+) -> ProgramResult {
+    sub_state_data.bid_endtime = cur_time
+        .checked_add(schedule.bid_window)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    sub_state_data.reveal_time = sub_state_data
+        .bid_endtime
+        .saturating_sub(schedule.reveal_window);
+    sub_state_data.winner_rank = 0;
+    sub_state_data.auction_start = cur_time;
+    Ok(())
+}
+
+// offer climbs linearly from the start rent and stays at max rent once the bid window is over
+fn dutch_offer(sub_state_data: &VaultUserSubscriptionState, start_rent: u64, cur_time: u64) -> u64 {
+    let ramp = sub_state_data
+        .bid_endtime
+        .saturating_sub(sub_state_data.auction_start);
+    let elapsed = cur_time.saturating_sub(sub_state_data.auction_start);
+
+    if elapsed >= ramp {
+        return sub_state_data.max_rent;
+    }
+
+    let spread = (sub_state_data.max_rent - start_rent) as u128;
+    start_rent + (spread * elapsed as u128 / ramp as u128) as u64
+}
+
+pub fn bid_commitment(bid_amount: u64, salt: &[u8; 32], bidder: &Pubkey) -> [u8; 32] {
+    hashv(&[&bid_amount.to_le_bytes(), salt, bidder.as_ref()]).to_bytes()
+}
+
+/// Stands in for the subscription of a `ReAttest` approval, the proof doesn't fit the message.
+pub fn attestation_commitment(attestation_proof: &str, measurement: &[u8; 32]) -> Pubkey {
+    Pubkey::new_from_array(hashv(&[attestation_proof.as_bytes(), measurement]).to_bytes())
+}
+
+/// Ranked bidders that let their claim window lapse lose the bond to the subscriber.
+fn forfeit_bid_bond(
+    program_id: &Pubkey,
+    bid_commit: &AccountInfo,
+    subscription: &Pubkey,
+    bidder: &Pubkey,
+    round: u64,
+    user_state_data: &mut VaultUserState,
+) -> ProgramResult {
+    let (bid_commit_pda, _) = Pubkey::find_program_address(
+        &[
+            BID_COMMIT.as_bytes(),
+            subscription.as_ref(),
+            bidder.as_ref(),
+        ],
+        program_id,
+    );
+
+    if bid_commit_pda != *bid_commit.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    // already closed by settle_bid, which forfeited the bond itself
+    if bid_commit.owner != program_id {
+        return Ok(());
+    }
+
+    let mut bid_commit_data =
+        try_from_slice_unchecked::<VaultBidCommitState>(&bid_commit.data.borrow()).unwrap();
+
+    if !bid_commit_data.is_initialized()
+        || bid_commit_data.forfeited
+        || bid_commit_data.round != round
+    {
+        return Ok(());
+    }
+
+    user_state_data.balance += bid_commit_data.bond;
+
+    bid_commit_data.forfeited = true;
+    bid_commit_data.serialize(&mut &mut bid_commit.data.borrow_mut()[..])?;
+
+    VaultEvent::BondForfeited {
+        subscription: *subscription,
+        bidder: *bidder,
+        bond: bid_commit_data.bond,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn rank_bid(bids: &mut Vec<RankedBid>, ranked_bid: RankedBid) {
+    // ties keep the earlier reveal ahead
+    let position = bids
+        .iter()
+        .position(|existing| ranked_bid.score > existing.score)
+        .unwrap_or(bids.len());
+
+    if position < MAX_RANKED_BIDS {
+        bids.insert(position, ranked_bid);
+        bids.truncate(MAX_RANKED_BIDS);
+    }
+}
+
+fn load_or_create_sub_bids<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    sub_state: &AccountInfo<'a>,
+    sub_bids: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    round: u64,
+) -> Result<VaultSubBidsState, ProgramError> {
+    let (sub_bids_pda, bump_seed) =
+        Pubkey::find_program_address(&[SUB_BIDS.as_bytes(), sub_state.key.as_ref()], program_id);
+
+    if sub_bids_pda != *sub_bids.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if sub_bids.data_is_empty() {
+        let state_size = 1 + 8 + (4 + MAX_RANKED_BIDS * (32 + 8 + 8));
+        let rent = Rent::get()?;
+        let rent_lamports = rent.minimum_balance(state_size);
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                sub_bids.key,
+                rent_lamports,
+                state_size.try_into().unwrap(),
+                program_id,
+            ),
+            &[payer.clone(), sub_bids.clone(), system_program.clone()],
+            &[&[SUB_BIDS.as_bytes(), sub_state.key.as_ref(), &[bump_seed]]],
+        )?;
+    }
+
+    if sub_bids.owner != program_id {
+        msg!("Wrong sub bids account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let mut sub_bids_data =
+        try_from_slice_unchecked::<VaultSubBidsState>(&sub_bids.data.borrow()).unwrap();
+
+    // ranking from an earlier round is stale once the subscription got re-auctioned
+    if !sub_bids_data.is_initialized() || sub_bids_data.round != round {
+        sub_bids_data.is_initialized = true;
+        sub_bids_data.round = round;
+        sub_bids_data.bids.clear();
+    }
+
+    Ok(sub_bids_data)
+}
+
+fn load_sub_bids(
+    program_id: &Pubkey,
+    sub_bids: &AccountInfo,
+    subscription: &Pubkey,
+) -> Result<VaultSubBidsState, ProgramError> {
+    if sub_bids.owner != program_id {
+        msg!("Wrong sub bids account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (sub_bids_pda, _) =
+        Pubkey::find_program_address(&[SUB_BIDS.as_bytes(), subscription.as_ref()], program_id);
+
+    if sub_bids_pda != *sub_bids.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let sub_bids_data =
+        try_from_slice_unchecked::<VaultSubBidsState>(&sub_bids.data.borrow()).unwrap();
+
+    if !sub_bids_data.is_initialized() {
+        msg!("No bids revealed yet!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(sub_bids_data)
+}
+
+fn load_bid_commit(
+    program_id: &Pubkey,
+    bid_commit: &AccountInfo,
+    subscription: &Pubkey,
+    bidder: &Pubkey,
+) -> Result<VaultBidCommitState, ProgramError> {
+    if bid_commit.owner != program_id {
+        msg!("Wrong bid commit account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (bid_commit_pda, _) = Pubkey::find_program_address(
+        &[
+            BID_COMMIT.as_bytes(),
+            subscription.as_ref(),
+            bidder.as_ref(),
+        ],
+        program_id,
+    );
+
+    if bid_commit_pda != *bid_commit.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let bid_commit_data =
+        try_from_slice_unchecked::<VaultBidCommitState>(&bid_commit.data.borrow()).unwrap();
+
+    if !bid_commit_data.is_initialized() {
+        msg!("Bid was never committed!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(bid_commit_data)
+}
+
+fn create_escrow<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    sub_state: &AccountInfo<'a>,
+    sub_escrow: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> Result<VaultSubEscrowState, ProgramError> {
+    let (sub_escrow_pda, bump_seed) =
+        Pubkey::find_program_address(&[SUB_ESCROW.as_bytes(), sub_state.key.as_ref()], program_id);
+
+    if sub_escrow_pda != *sub_escrow.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let state_size = 1 + 8;
+    let rent = Rent::get()?;
+    let rent_lamports = rent.minimum_balance(state_size);
+
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            sub_escrow.key,
+            rent_lamports,
+            state_size.try_into().unwrap(),
+            program_id,
+        ),
+        &[payer.clone(), sub_escrow.clone(), system_program.clone()],
+        &[&[SUB_ESCROW.as_bytes(), sub_state.key.as_ref(), &[bump_seed]]],
+    )?;
+
+    let mut sub_escrow_data =
+        try_from_slice_unchecked::<VaultSubEscrowState>(&sub_escrow.data.borrow()).unwrap();
+
+    if sub_escrow_data.is_initialized() {
+        msg!("Escrow already initalised");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    sub_escrow_data.is_initialized = true;
+    Ok(sub_escrow_data)
+}
+
+fn load_escrow(
+    program_id: &Pubkey,
+    sub_escrow: &AccountInfo,
+    sub_state: &Pubkey,
+) -> Result<VaultSubEscrowState, ProgramError> {
+    if sub_escrow.owner != program_id {
+        msg!("Wrong escrow account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (sub_escrow_pda, _) =
+        Pubkey::find_program_address(&[SUB_ESCROW.as_bytes(), sub_state.as_ref()], program_id);
+
+    if sub_escrow_pda != *sub_escrow.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let sub_escrow_data =
+        try_from_slice_unchecked::<VaultSubEscrowState>(&sub_escrow.data.borrow()).unwrap();
+
+    if !sub_escrow_data.is_initialized() {
+        msg!("Escrow not found!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(sub_escrow_data)
+}
+
+fn load_config(
+    program_id: &Pubkey,
+    protocol_config: &AccountInfo,
+) -> Result<ProtocolConfig, ProgramError> {
+    if protocol_config.owner != program_id {
+        msg!("Wrong protocol config account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (protocol_config_pda, _) =
+        Pubkey::find_program_address(&[PROTOCOL_CONFIG.as_bytes()], program_id);
+
+    if protocol_config_pda != *protocol_config.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let protocol_config_data =
+        try_from_slice_unchecked::<ProtocolConfig>(&protocol_config.data.borrow()).unwrap();
+
+    if !protocol_config_data.is_initialized() {
+        msg!("Protocol not initialized!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(protocol_config_data)
+}
+
+fn load_app(
+    program_id: &Pubkey,
+    app_state: &AccountInfo,
+    app_id: u64,
+) -> Result<VaultAppState, ProgramError> {
+    if app_state.owner != program_id {
+        msg!("Wrong app state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (app_state_pda, _) = Pubkey::find_program_address(
+        &[APP_STATE.as_bytes(), app_id.to_be_bytes().as_ref()],
+        program_id,
+    );
+
+    if app_state_pda != *app_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let app_state_data =
+        try_from_slice_unchecked::<VaultAppState>(&app_state.data.borrow()).unwrap();
+
+    if !app_state_data.is_initialized() {
+        msg!("given app not found");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(app_state_data)
+}
+
+fn or_default(value: u64, default: u64) -> u64 {
+    if value == 0 {
+        default
+    } else {
+        value
+    }
+}
+
+fn check_params(params: &ProtocolParams) -> ProgramResult {
+    if params.slash_bps > MAX_BPS
+        || params.reporter_share_bps > MAX_BPS
+        || params.reputation_weight_bps > MAX_BPS
+        || params.protocol_fee_bps > MAX_BPS
+    {
+        msg!("Rates must be in basis points");
+        return Err(VaultError::InvalidBasisPoints.into());
+    }
+
+    // the defaults fill in app schedules, so they have to pass the same cap
+    let defaults = AppSchedule {
+        report_interval: params.report_interval,
+        grace_period: params.report_grace,
+        bid_window: params.bid_window,
+        claim_window: params.claim_window,
+        reveal_window: params.reveal_window,
+    };
+    if !within_schedule_cap(&defaults, params.max_schedule_window) {
+        msg!("Default schedule windows exceed the protocol maximum");
+        return Err(VaultError::InvalidSchedule.into());
+    }
+
+    Ok(())
+}
+
+// app creators pick their own windows, capping them keeps every deadline sum far from u64::MAX
+fn within_schedule_cap(schedule: &AppSchedule, max_schedule_window: u64) -> bool {
+    [
+        schedule.report_interval,
+        schedule.grace_period,
+        schedule.bid_window,
+        schedule.claim_window,
+        schedule.reveal_window,
+    ]
+    .iter()
+    .all(|window| *window <= max_schedule_window)
+}
+
+fn check_signer_set(signers: &[Pubkey], threshold: u8) -> Result<(), ProgramError> {
+    let distinct = signers
+        .iter()
+        .enumerate()
+        .all(|(i, signer)| *signer != Pubkey::default() && !signers[..i].contains(signer));
+
+    if !distinct
+        || signers.len() > MAX_CONSENSUS_SIGNERS
+        || threshold == 0
+        || threshold as usize > signers.len()
+    {
+        msg!("Invalid consesues signer set or threshold");
+        return Err(VaultError::InvalidSignerSet.into());
+    }
+
+    Ok(())
+}
+
+fn check_program_treasury(
+    program_id: &Pubkey,
+    program_treasury: &AccountInfo,
+    program_ata: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    if program_treasury.owner != program_id {
+        msg!("Wrong treasury account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (program_treasury_pda, bump_seed) =
+        Pubkey::find_program_address(&[TREASURY_STATE.as_bytes()], program_id);
+
+    if program_treasury_pda != *program_treasury.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    if !is_ata_owner(program_treasury.key, program_ata) {
+        msg!("Wrong treasury ata account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    Ok(bump_seed)
+}
+
+fn load_operator(
+    program_id: &Pubkey,
+    operator_state: &AccountInfo,
+    operator: &Pubkey,
+) -> Result<VaultOperatorState, ProgramError> {
+    if operator_state.owner != program_id {
+        msg!("Wrong operator state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (operator_state_pda, _) =
+        Pubkey::find_program_address(&[OPERATOR_STATE.as_bytes(), operator.as_ref()], program_id);
+
+    if operator_state_pda != *operator_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let operator_state_data =
+        try_from_slice_unchecked::<VaultOperatorState>(&operator_state.data.borrow()).unwrap();
+
+    if !operator_state_data.is_initialized() {
+        msg!("Executor is not a registered operator!");
+        return Err(VaultError::OperatorNotStaked.into());
+    }
+
+    Ok(operator_state_data)
+}
+
+fn load_reputation(
+    program_id: &Pubkey,
+    reputation_state: &AccountInfo,
+    operator: &Pubkey,
+) -> Result<VaultReputationState, ProgramError> {
+    if reputation_state.owner != program_id {
+        msg!("Wrong reputation state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (reputation_state_pda, _) = Pubkey::find_program_address(
+        &[REPUTATION_STATE.as_bytes(), operator.as_ref()],
+        program_id,
+    );
+
+    if reputation_state_pda != *reputation_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let reputation_state_data =
+        try_from_slice_unchecked::<VaultReputationState>(&reputation_state.data.borrow()).unwrap();
+
+    if !reputation_state_data.is_initialized() {
+        msg!("Operator reputation not found!");
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    Ok(reputation_state_data)
+}
+
+fn update_reputation(
+    program_id: &Pubkey,
+    reputation_state: &AccountInfo,
+    operator: &Pubkey,
+    on_time: bool,
+) -> ProgramResult {
+    let mut reputation_state_data = load_reputation(program_id, reputation_state, operator)?;
+
+    if on_time {
+        reputation_state_data.score =
+            (reputation_state_data.score + REPUTATION_REWARD).min(MAX_REPUTATION);
+        reputation_state_data.on_time_reports += 1;
+    } else {
+        reputation_state_data.score = reputation_state_data
+            .score
+            .saturating_sub(REPUTATION_PENALTY);
+        reputation_state_data.faults += 1;
+    }
+
+    reputation_state_data.serialize(&mut &mut reputation_state.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+// cheaper bids and better reputation both raise the score, reputation_weight_bps decides how much each one counts
+fn bid_score(bid_amount: u64, max_rent: u64, reputation: u64, reputation_weight_bps: u16) -> u64 {
+    let price_score = if max_rent == 0 {
+        0
+    } else {
+        (max_rent - bid_amount) as u128 * MAX_REPUTATION as u128 / max_rent as u128
+    };
+    let reputation_weight = reputation_weight_bps as u128;
+
+    ((price_score * (MAX_BPS as u128 - reputation_weight) + reputation as u128 * reputation_weight)
+        / MAX_BPS as u128) as u64
+}
+
+fn slash_executor(
+    program_id: &Pubkey,
+    executor_operator: &AccountInfo,
+    executor: &Pubkey,
+    subscription: &Pubkey,
+    reporter: Option<&Pubkey>,
+    params: &ProtocolParams,
+) -> Result<(u64, u64), ProgramError> {
+    let mut executor_operator_data = load_operator(program_id, executor_operator, executor)?;
+
+    let slashed_amount =
+        (executor_operator_data.stake as u128 * params.slash_bps as u128 / MAX_BPS as u128) as u64;
+    let reporter_amount = match reporter {
+        Some(_) => {
+            (slashed_amount as u128 * params.reporter_share_bps as u128 / MAX_BPS as u128) as u64
+        }
+        None => 0,
+    };
+    let subscriber_amount = slashed_amount - reporter_amount;
+
+    executor_operator_data.stake -= slashed_amount;
+    executor_operator_data.serialize(&mut &mut executor_operator.data.borrow_mut()[..])?;
+
+    VaultEvent::ExecutorSlashed {
+        executor: *executor,
+        subscription: *subscription,
+        reporter: reporter.copied(),
+        slashed_amount,
+        subscriber_amount,
+        reporter_amount,
+        remaining_stake: executor_operator_data.stake,
+    }
+    .emit();
+
+    Ok((subscriber_amount, reporter_amount))
+}
@@ -1,40 +1,125 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::{
-    program_pack::IsInitialized,
-    pubkey::Pubkey,
-};
+use solana_program::{program_pack::IsInitialized, pubkey::Pubkey};
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultMetaDataState {
     pub is_initialized: bool,
     pub attestation_proof: String,
-    pub vault_public_key: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub next_signers: Vec<Pubkey>,
+    pub next_threshold: u8,
+    pub rotation_epoch: u64,
+    pub grace_end_epoch: u64,
+    pub measurement: [u8; 32],
+    pub attestation_expiry: u64,
 }
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct ProtocolParams {
+    pub bid_window: u64,
+    pub claim_window: u64,
+    pub report_interval: u64,
+    pub report_grace: u64,
+    pub min_topup: u64,
+    pub max_attestation_len: u32,
+    pub max_ipfs_hash_len: u32,
+    pub max_params_hash_len: u32,
+    pub slash_bps: u16,
+    pub reporter_share_bps: u16,
+    pub reputation_weight_bps: u16,
+    pub keeper_bounty: u64,
+    pub protocol_fee_bps: u16,
+    pub key_rotation_grace: u64,
+    pub attestation_ttl: u64,
+    pub reveal_window: u64,
+    pub bid_bond: u64,
+    pub max_schedule_window: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ProtocolConfig {
+    pub is_initialized: bool,
+    pub admin: Pubkey,
+    pub params: ProtocolParams,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ConsensusAction {
+    Bid,
+    ClaimBid,
+    ReportWork,
+    RotateConsensus,
+    ReAttest,
+}
+
+/// Payload the consensus set signs. `value` is the bid amount for `Bid`, the work nonce for
+/// `ReportWork`, the activation epoch for `RotateConsensus`, the attestation expiry being
+/// replaced for `ReAttest` and zero for `ClaimBid`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct ConsensusMessage {
+    pub version: u8,
+    pub action: ConsensusAction,
+    pub program_id: Pubkey,
+    pub subscription: Pubkey,
+    pub operator: Pubkey,
+    pub operator_nonce: u64,
+    pub value: u64,
+    pub expiry_slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultMeasurementRegistryState {
+    pub is_initialized: bool,
+    pub measurements: Vec<[u8; 32]>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultTreasuryState {
+    pub is_initialized: bool,
+    pub accrued_fees: u64,
+    pub swept_fees: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultAppCounterState {
     pub is_initialized: bool,
     pub counter: u64,
 }
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AuctionMode {
+    SealedBid,
+    ReverseDutch { start_rent: u64 },
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AppSchedule {
+    pub report_interval: u64,
+    pub grace_period: u64,
+    pub bid_window: u64,
+    pub claim_window: u64,
+    pub reveal_window: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultAppState {
     pub is_initialized: bool,
     pub ipfs_hash: String,
     pub rent: u64,
-    pub creator_ata: Pubkey
+    pub creator_ata: Pubkey,
+    pub schedule: AppSchedule,
 }
 
-
-
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultUserState {
     pub is_initialized: bool,
     pub count: u64,
-    pub balance: u64
+    pub balance: u64,
+    pub reserved: u64,
 }
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultUserSubscriptionState {
     pub id: u64,
     pub is_initialized: bool,
@@ -48,13 +133,73 @@ pub struct VaultUserSubscriptionState {
     pub rent: u64,
     pub nonce: u64,
     pub last_report_time: u64,
-    pub restart: bool
+    pub restart: bool,
+    pub bid_score: u64,
+    pub has_escrow: bool,
+    pub creator_rent: u64,
+    pub reveal_time: u64,
+    pub auction_round: u64,
+    pub winner_rank: u8,
+    pub auction_mode: AuctionMode,
+    pub auction_start: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+pub struct RankedBid {
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub score: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultSubBidsState {
+    pub is_initialized: bool,
+    pub round: u64,
+    pub bids: Vec<RankedBid>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultBidCommitState {
+    pub is_initialized: bool,
+    pub subscription: Pubkey,
+    pub bidder: Pubkey,
+    pub round: u64,
+    pub commitment: [u8; 32],
+    pub bond: u64,
+    pub revealed: bool,
+    pub amount: u64,
+    pub forfeited: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultSubEscrowState {
+    pub is_initialized: bool,
+    pub balance: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultOperatorState {
+    pub is_initialized: bool,
+    pub attestation_proof: String,
+    pub transit_key: Pubkey,
+    pub stake: u64,
+    pub registered_at: u64,
+    pub measurement: [u8; 32],
+    pub attestation_expiry: u64,
 }
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VaultReputationState {
+    pub is_initialized: bool,
+    pub score: u64,
+    pub on_time_reports: u64,
+    pub faults: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct VaultBidderState {
     pub is_initialized: bool,
-    pub nonce: u64
+    pub nonce: u64,
 }
 
 impl IsInitialized for VaultMetaDataState {
@@ -63,6 +208,36 @@ impl IsInitialized for VaultMetaDataState {
     }
 }
 
+impl IsInitialized for ProtocolConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultMeasurementRegistryState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultSubBidsState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultBidCommitState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultTreasuryState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
 impl IsInitialized for VaultAppCounterState {
     fn is_initialized(&self) -> bool {
         self.is_initialized
@@ -87,10 +262,26 @@ impl IsInitialized for VaultUserSubscriptionState {
     }
 }
 
-
 impl IsInitialized for VaultBidderState {
     fn is_initialized(&self) -> bool {
         self.is_initialized
     }
 }
 
+impl IsInitialized for VaultOperatorState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultReputationState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for VaultSubEscrowState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
@@ -1,35 +1,140 @@
-use crate::{error::VaultError, state::VaultMetaDataState};
+use crate::{
+    error::VaultError,
+    state::{
+        ConsensusMessage, VaultMeasurementRegistryState, VaultMetaDataState, VaultOperatorState,
+    },
+};
 use solana_program::ed25519_program::ID as ED25519_ID;
 use solana_program::sysvar::instructions::{load_instruction_at_checked, ID as IX_ID};
 use solana_program::{
     account_info::AccountInfo,
     borsh1::try_from_slice_unchecked,
+    clock::Clock,
     instruction::Instruction,
     msg,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
+    sysvar::Sysvar,
 };
 use spl_token::state::Account;
 
+pub const CONSENSUS_MSG_VERSION: u8 = 1;
+
 pub fn is_ata_owner(onwer_acc: &Pubkey, ata_acc: &AccountInfo) -> bool {
     match Account::unpack(&ata_acc.data.borrow()) {
-        Ok(acc) => return acc.owner == *onwer_acc,
-        _ => {
-            return false;
-        }
+        Ok(acc) => acc.owner == *onwer_acc,
+        _ => false,
+    }
+}
+
+pub fn is_staked_operator(
+    operator_seed: &str,
+    operator: &Pubkey,
+    operator_state: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    if operator_state.owner != program_id {
+        msg!("Wrong operator state account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (operator_state_pda, _) =
+        Pubkey::find_program_address(&[operator_seed.as_bytes(), operator.as_ref()], program_id);
+
+    if operator_state_pda != *operator_state.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let operator_state_data =
+        try_from_slice_unchecked::<VaultOperatorState>(&operator_state.data.borrow()).unwrap();
+
+    if !operator_state_data.is_initialized() || operator_state_data.stake == 0 {
+        msg!("Operator not registered or has no locked stake");
+        return Err(VaultError::OperatorNotStaked.into());
+    }
+
+    Ok(())
+}
+
+pub fn is_eligible_operator(
+    operator_seed: &str,
+    registry_seed: &str,
+    operator: &Pubkey,
+    operator_state: &AccountInfo,
+    measurement_registry: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<VaultOperatorState, ProgramError> {
+    is_staked_operator(operator_seed, operator, operator_state, program_id)?;
+
+    let operator_state_data =
+        try_from_slice_unchecked::<VaultOperatorState>(&operator_state.data.borrow()).unwrap();
+
+    is_approved_measurement(
+        registry_seed,
+        measurement_registry,
+        &operator_state_data.measurement,
+        program_id,
+    )?;
+
+    Ok(operator_state_data)
+}
+
+pub fn is_attestation_lapsed(attestation_expiry: u64) -> Result<bool, ProgramError> {
+    Ok(Clock::get()?.unix_timestamp as u64 >= attestation_expiry)
+}
+
+pub fn is_approved_measurement(
+    registry_seed: &str,
+    measurement_registry: &AccountInfo,
+    measurement: &[u8; 32],
+    program_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    if is_revoked_measurement(registry_seed, measurement_registry, measurement, program_id)? {
+        msg!("Enclave measurement not in allowlist");
+        return Err(VaultError::MeasurementNotApproved.into());
     }
+
+    Ok(())
+}
+
+/// Checks the registry account itself, a measurement missing from it is reported as `true`.
+pub fn is_revoked_measurement(
+    registry_seed: &str,
+    measurement_registry: &AccountInfo,
+    measurement: &[u8; 32],
+    program_id: &Pubkey,
+) -> Result<bool, ProgramError> {
+    if measurement_registry.owner != program_id {
+        msg!("Wrong measurement registry account provided");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (registry_pda, _) = Pubkey::find_program_address(&[registry_seed.as_bytes()], program_id);
+
+    if registry_pda != *measurement_registry.key {
+        msg!("Invalid seeds for PDA");
+        return Err(VaultError::InvalidPDA.into());
+    }
+
+    let registry_data = try_from_slice_unchecked::<VaultMeasurementRegistryState>(
+        &measurement_registry.data.borrow(),
+    )
+    .unwrap();
+
+    Ok(!registry_data.is_initialized() || !registry_data.measurements.contains(measurement))
 }
 
 pub fn is_valid_consesues(
     vault_metadata: &str,
+    registry_seed: &str,
     ix_sysvar: &AccountInfo,
-    consensus: &AccountInfo,
     metadata: &AccountInfo,
+    measurement_registry: &AccountInfo,
     program_id: &Pubkey,
-    raw_msg: &[u8],
-    _signature: &[u8],
-) -> Result<(), ProgramError> {
+    message: &ConsensusMessage,
+) -> Result<Vec<Pubkey>, ProgramError> {
     let (metadata_pda, _) = Pubkey::find_program_address(&[vault_metadata.as_bytes()], program_id);
 
     if metadata_pda != *metadata.key {
@@ -45,9 +150,16 @@ pub fn is_valid_consesues(
         return Err(ProgramError::UninitializedAccount);
     }
 
-    if metadata_data.vault_public_key != *consensus.key {
-        msg!("Wrong consesues key provided");
-        return Err(VaultError::InvalidConsesues.into());
+    is_approved_measurement(
+        registry_seed,
+        measurement_registry,
+        &metadata_data.measurement,
+        program_id,
+    )?;
+
+    if is_attestation_lapsed(metadata_data.attestation_expiry)? {
+        msg!("Consesues attestation lapsed");
+        return Err(VaultError::AttestationExpired.into());
     }
 
     if *ix_sysvar.key != IX_ID {
@@ -55,87 +167,182 @@ pub fn is_valid_consesues(
         return Err(ProgramError::UnsupportedSysvar);
     }
 
-    let ix: Instruction = load_instruction_at_checked(0, ix_sysvar)?;
-    verify_ed25519_ix(
-        &ix,
-        consensus.key.to_bytes().as_ref(),
-        raw_msg,
-        _signature,
-    )
+    if message.version != CONSENSUS_MSG_VERSION || message.program_id != *program_id {
+        msg!("Consesues message not meant for this program");
+        return Err(VaultError::InvalidConsesues.into());
+    }
+
+    let clock = Clock::get()?;
+
+    if clock.slot > message.expiry_slot {
+        msg!("Consesues approval expired at slot {}", message.expiry_slot);
+        return Err(VaultError::ConsesuesMessageExpired.into());
+    }
+
+    let raw_msg = borsh::to_vec(message)?;
+
+    let approvals = collect_ed25519_approvals(ix_sysvar, &raw_msg)?;
+
+    let epoch = clock.epoch;
+
+    if !is_approved_by_consesues(&metadata_data, &approvals, epoch) {
+        msg!("Consesues threshold not reached");
+        return Err(VaultError::InvalidConsesues.into());
+    }
+
+    Ok(approvals)
+}
+
+/// Outgoing set stays valid until the grace window ends, incoming set from its activation epoch.
+pub fn is_approved_by_consesues(
+    metadata: &VaultMetaDataState,
+    approvals: &[Pubkey],
+    epoch: u64,
+) -> bool {
+    if metadata.next_signers.is_empty() {
+        return has_quorum(&metadata.signers, metadata.threshold, approvals);
+    }
+
+    (epoch < metadata.grace_end_epoch
+        && has_quorum(&metadata.signers, metadata.threshold, approvals))
+        || (epoch >= metadata.rotation_epoch
+            && has_quorum(&metadata.next_signers, metadata.next_threshold, approvals))
+}
+
+pub fn has_quorum(signers: &[Pubkey], threshold: u8, approvals: &[Pubkey]) -> bool {
+    let approved = signers
+        .iter()
+        .filter(|signer| approvals.contains(signer))
+        .count();
+
+    threshold > 0 && approved >= threshold as usize
+}
+
+/// Scans the whole transaction so compute budget or other instructions can come first.
+pub fn collect_ed25519_approvals(
+    ix_sysvar: &AccountInfo,
+    msg: &[u8],
+) -> Result<Vec<Pubkey>, ProgramError> {
+    // The instructions sysvar starts with the instruction count as a little endian u16
+    let num_instructions = {
+        let data = ix_sysvar.try_borrow_data()?;
+        if data.len() < 2 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        u16::from_le_bytes([data[0], data[1]]) as usize
+    };
+
+    let mut found = false;
+    let mut approvals: Vec<Pubkey> = Vec::new();
+
+    for index in 0..num_instructions {
+        let ix: Instruction = load_instruction_at_checked(index, ix_sysvar)?;
+        if ix.program_id != ED25519_ID {
+            continue;
+        }
+        found = true;
+
+        for signer in verify_ed25519_ix(ix_sysvar, &ix, msg)? {
+            if !approvals.contains(&signer) {
+                approvals.push(signer);
+            }
+        }
+    }
+
+    if !found {
+        msg!("No ed25519 verify instruction in transaction");
+        return Err(VaultError::SigVerificationFailed.into());
+    }
+
+    Ok(approvals)
 }
 
 pub fn verify_ed25519_ix(
+    ix_sysvar: &AccountInfo,
     ix: &Instruction,
-    pubkey: &[u8],
     msg: &[u8],
-    sig: &[u8],
-) -> Result<(), ProgramError> {
-    if ix.program_id       != ED25519_ID                   ||  // The program id we expect
-        ix.accounts.len()   != 0                            ||  // With no context accounts
-        ix.data.len()       != (16 + 64 + 32 + msg.len())
-    // And data of this size
-    {
-        return Err(VaultError::SigVerificationFailed.into()); // Otherwise, we can already throw err
+) -> Result<Vec<Pubkey>, ProgramError> {
+    if ix.program_id != ED25519_ID || !ix.accounts.is_empty() {
+        return Err(VaultError::SigVerificationFailed.into());
     }
 
-    check_ed25519_data(&ix.data, pubkey, msg, sig)?; // If that's not the case, check data
-
-    Ok(())
+    check_ed25519_data(ix_sysvar, &ix.data, msg)
 }
 
+/// Returns the distinct keys that signed `msg`. Signatures themselves were already checked by the precompile.
 pub fn check_ed25519_data(
+    ix_sysvar: &AccountInfo,
     data: &[u8],
-    pubkey: &[u8],
     msg: &[u8],
-    sig: &[u8],
-) -> Result<(), ProgramError> {
+) -> Result<Vec<Pubkey>, ProgramError> {
     // According to this layout used by the Ed25519Program
     // https://github.com/solana-labs/solana-web3.js/blob/master/src/ed25519-program.ts#L33
 
-    // "Deserializing" byte slices
-
-    let num_signatures = &[data[0]]; // Byte  0
-    let padding = &[data[1]]; // Byte  1
-    let signature_offset = &data[2..=3]; // Bytes 2,3
-    let signature_instruction_index = &data[4..=5]; // Bytes 4,5
-    let public_key_offset = &data[6..=7]; // Bytes 6,7
-    let public_key_instruction_index = &data[8..=9]; // Bytes 8,9
-    let message_data_offset = &data[10..=11]; // Bytes 10,11
-    let message_data_size = &data[12..=13]; // Bytes 12,13
-    let message_instruction_index = &data[14..=15]; // Bytes 14,15
-
-    let data_pubkey = &data[16..16 + 32]; // Bytes 16..16+32
-    let data_sig = &data[48..48 + 64]; // Bytes 48..48+64
-    let data_msg = &data[112..]; // Bytes 112..end
-
-    // Expected values
-
-    let exp_public_key_offset: u16 = 16; // 2*u8 + 7*u16
-    let exp_signature_offset: u16 = exp_public_key_offset + pubkey.len() as u16;
-    let exp_message_data_offset: u16 = exp_signature_offset + sig.len() as u16;
-    let exp_num_signatures: u8 = 1;
-    let exp_message_data_size: u16 = msg.len().try_into().unwrap();
-
-    // Header and Arg Checks
-
-    // Header
-    if num_signatures != &exp_num_signatures.to_le_bytes()
-        || padding != &[0]
-        || signature_offset != &exp_signature_offset.to_le_bytes()
-        || signature_instruction_index != &u16::MAX.to_le_bytes()
-        || public_key_offset != &exp_public_key_offset.to_le_bytes()
-        || public_key_instruction_index != &u16::MAX.to_le_bytes()
-        || message_data_offset != &exp_message_data_offset.to_le_bytes()
-        || message_data_size != &exp_message_data_size.to_le_bytes()
-        || message_instruction_index != &u16::MAX.to_le_bytes()
-    {
+    // Header: num_signatures (u8), padding (u8), then 7*u16 offsets per signature
+
+    const OFFSETS_START: usize = 2;
+    const OFFSETS_SIZE: usize = 14;
+
+    if data.len() < OFFSETS_START {
         return Err(VaultError::SigVerificationFailed.into());
     }
 
-    // Arguments
-    if data_pubkey != pubkey || data_msg != msg || data_sig != sig {
+    let num_signatures = data[0] as usize;
+    let padding = data[1];
+
+    if num_signatures == 0
+        || padding != 0
+        || data.len() < OFFSETS_START + num_signatures * OFFSETS_SIZE
+    {
         return Err(VaultError::SigVerificationFailed.into());
     }
 
-    Ok(())
+    // u16::MAX points at the verify instruction itself, anything else at another instruction
+    let resolve =
+        |instruction_index: u16, offset: usize, size: usize| -> Result<Vec<u8>, ProgramError> {
+            let bytes = if instruction_index == u16::MAX {
+                data.get(offset..offset + size).map(<[u8]>::to_vec)
+            } else {
+                load_instruction_at_checked(instruction_index as usize, ix_sysvar)?
+                    .data
+                    .get(offset..offset + size)
+                    .map(<[u8]>::to_vec)
+            };
+            bytes.ok_or_else(|| VaultError::SigVerificationFailed.into())
+        };
+
+    let mut signers: Vec<Pubkey> = Vec::with_capacity(num_signatures);
+
+    for i in 0..num_signatures {
+        let start = OFFSETS_START + i * OFFSETS_SIZE;
+        let offsets = &data[start..start + OFFSETS_SIZE];
+        let read_u16 = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        // Bytes 0..4 hold the signature offset and index, only the precompile needs those
+        let public_key_offset = read_u16(4) as usize; // Bytes 4,5
+        let public_key_instruction_index = read_u16(6); // Bytes 6,7
+        let message_data_offset = read_u16(8) as usize; // Bytes 8,9
+        let message_data_size = read_u16(10) as usize; // Bytes 10,11
+        let message_instruction_index = read_u16(12); // Bytes 12,13
+
+        let data_msg = resolve(
+            message_instruction_index,
+            message_data_offset,
+            message_data_size,
+        )?;
+
+        // Signatures over other messages are ignored, they just don't count towards the threshold
+        if data_msg != msg {
+            continue;
+        }
+
+        let data_pubkey = resolve(public_key_instruction_index, public_key_offset, 32)?;
+        let signer = Pubkey::try_from(data_pubkey.as_slice())
+            .map_err(|_| VaultError::SigVerificationFailed)?;
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
+    Ok(signers)
 }
@@ -0,0 +1,127 @@
+use std::{fs, path::PathBuf, process::Command};
+
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+
+// nothing listens here, so any RPC call fails the command
+const UNREACHABLE_URL: &str = "http://127.0.0.1:9";
+
+struct Workdir(PathBuf);
+
+impl Workdir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("smart-vault-{name}-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn keypair(&self, name: &str) -> (Keypair, String) {
+        let keypair = Keypair::new();
+        let path = self.0.join(format!("{name}.json"));
+        write_keypair_file(&keypair, &path).unwrap();
+        (keypair, path.display().to_string())
+    }
+}
+
+impl Drop for Workdir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn smart_vault(payer: &str, output: &PathBuf, args: &[&str]) -> std::process::Output {
+    let program_id = Pubkey::new_unique().to_string();
+    let treasury_ata = Pubkey::new_unique().to_string();
+    let blockhash = Hash::new_unique().to_string();
+
+    Command::new(env!("CARGO_BIN_EXE_smart-vault"))
+        .args(["--url", UNREACHABLE_URL, "--keypair", payer])
+        .args(["--program-id", &program_id, "--treasury-ata", &treasury_ata])
+        .args(["--blockhash", &blockhash, "--output"])
+        .arg(output)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn report_work_is_written_without_reaching_the_cluster() {
+    let workdir = Workdir::new("report-work");
+    let (payer, payer_path) = workdir.keypair("payer");
+    let (_, consensus_path) = workdir.keypair("consensus");
+    let output = workdir.0.join("report-work.tx");
+    let user = Pubkey::new_unique().to_string();
+    let ata = Pubkey::new_unique().to_string();
+
+    let result = smart_vault(
+        &payer_path,
+        &output,
+        &[
+            "report-work",
+            "--user",
+            &user,
+            "--sub-id",
+            "0",
+            "--app-id",
+            "1",
+            "--nonce",
+            "3",
+            "--bid-winner-ata",
+            &ata,
+            "--creator-ata",
+            &ata,
+            "--consensus-keypair",
+            &consensus_path,
+            "--expiry-slot",
+            "100",
+            "--operator-nonce",
+            "0",
+        ],
+    );
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+
+    let transaction: Transaction = bincode::deserialize(&fs::read(&output).unwrap()).unwrap();
+    transaction.verify().unwrap();
+    assert_eq!(transaction.message.account_keys[0], payer.pubkey());
+    assert_eq!(transaction.message.instructions.len(), 2);
+}
+
+#[test]
+fn claimbid_without_values_from_the_cluster_is_rejected() {
+    let workdir = Workdir::new("claimbid");
+    let (_, payer_path) = workdir.keypair("payer");
+    let (_, consensus_path) = workdir.keypair("consensus");
+    let output = workdir.0.join("claimbid.tx");
+    let user = Pubkey::new_unique().to_string();
+
+    let result = smart_vault(
+        &payer_path,
+        &output,
+        &[
+            "claimbid",
+            "--user",
+            &user,
+            "--sub-id",
+            "0",
+            "--app-id",
+            "1",
+            "--outranked-by",
+            "--consensus-keypair",
+            &consensus_path,
+            "--operator-nonce",
+            "0",
+        ],
+    );
+
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("--expiry-slot is required"));
+    assert!(!output.exists());
+}
@@ -0,0 +1,749 @@
+#![allow(dead_code)]
+
+use smart_vault::{
+    client::{
+        attestation_commitment, bid_commitment, ed25519_verify_instruction, VaultAccount,
+        VaultClient,
+    },
+    error::VaultError,
+    processor::process_instruction,
+    state::{
+        AppSchedule, AuctionMode, ConsensusAction, ConsensusMessage, ProtocolParams,
+        VaultAppCounterState, VaultBidderState, VaultOperatorState, VaultReputationState,
+        VaultSubBidsState, VaultUserState, VaultUserSubscriptionState,
+    },
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+pub const MIN_TOPUP: u64 = 10;
+pub const BID_WINDOW: u64 = 100;
+pub const REVEAL_WINDOW: u64 = 40;
+pub const CLAIM_WINDOW: u64 = 50;
+pub const REPORT_INTERVAL: u64 = 60;
+pub const REPORT_GRACE: u64 = 30;
+pub const ATTESTATION_TTL: u64 = 100_000;
+pub const BID_BOND: u64 = 10;
+pub const MAX_SCHEDULE_WINDOW: u64 = 1_000_000;
+pub const KEEPER_BOUNTY: u64 = 5;
+pub const SLASH_BPS: u16 = 1_000;
+pub const REPORTER_SHARE_BPS: u16 = 5_000;
+pub const PROTOCOL_FEE_BPS: u16 = 500;
+pub const MAX_INPUT_LEN: u32 = 64;
+
+pub const APP_RENT: u64 = 20;
+pub const MAX_RENT: u64 = 80;
+pub const STAKE: u64 = 1_000;
+pub const DEPOSIT: u64 = 1_000;
+
+pub const MEASUREMENT: [u8; 32] = [7; 32];
+/// Approved for operators only, so revoking it leaves the consensus attestation intact.
+pub const OPERATOR_MEASUREMENT: [u8; 32] = [8; 32];
+pub const SALT: [u8; 32] = [42; 32];
+/// Consensus approvals stay valid for this many slots after they are signed.
+pub const APPROVAL_TTL: u64 = 10;
+
+const ACTOR_LAMPORTS: u64 = 10_000_000_000;
+
+pub fn params() -> ProtocolParams {
+    ProtocolParams {
+        bid_window: BID_WINDOW,
+        claim_window: CLAIM_WINDOW,
+        report_interval: REPORT_INTERVAL,
+        report_grace: REPORT_GRACE,
+        min_topup: MIN_TOPUP,
+        max_attestation_len: MAX_INPUT_LEN,
+        max_ipfs_hash_len: MAX_INPUT_LEN,
+        max_params_hash_len: MAX_INPUT_LEN,
+        slash_bps: SLASH_BPS,
+        reporter_share_bps: REPORTER_SHARE_BPS,
+        reputation_weight_bps: 2_000,
+        keeper_bounty: KEEPER_BOUNTY,
+        protocol_fee_bps: PROTOCOL_FEE_BPS,
+        key_rotation_grace: 2,
+        attestation_ttl: ATTESTATION_TTL,
+        reveal_window: REVEAL_WINDOW,
+        bid_bond: BID_BOND,
+        max_schedule_window: MAX_SCHEDULE_WINDOW,
+    }
+}
+
+/// Zero values fall back to the protocol defaults from [`params`].
+pub fn default_schedule() -> AppSchedule {
+    AppSchedule {
+        report_interval: 0,
+        grace_period: 0,
+        bid_window: 0,
+        claim_window: 0,
+        reveal_window: 0,
+    }
+}
+
+/// Ed25519 verify instruction with every key in `signers` approving `message`.
+pub fn sign_approval(signers: &[&Keypair], message: &ConsensusMessage) -> Instruction {
+    let raw_msg = borsh::to_vec(message).unwrap();
+    let approvals: Vec<(Pubkey, [u8; 64])> = signers
+        .iter()
+        .map(|signer| {
+            let signature = signer.sign_message(&raw_msg);
+            (signer.pubkey(), signature.as_ref().try_into().unwrap())
+        })
+        .collect();
+
+    ed25519_verify_instruction(&approvals, &raw_msg)
+}
+
+pub fn assert_vault_error(result: Result<(), BanksClientError>, expected: VaultError) {
+    let expected_code = expected as u32;
+    match result.map_err(|error| error.unwrap()) {
+        Err(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+            assert_eq!(code, expected_code, "unexpected vault error code")
+        }
+        other => panic!("expected vault error {}, got {:?}", expected_code, other),
+    }
+}
+
+/// Wallet with lamports for account rent and a token account of the test mint.
+pub struct Actor {
+    pub keypair: Keypair,
+    pub ata: Pubkey,
+}
+
+impl Actor {
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+}
+
+/// Program loaded natively into an in-process bank, together with a mock mint and a single
+/// key consensus set.
+pub struct Vault {
+    pub context: ProgramTestContext,
+    pub client: VaultClient,
+    pub mint: Pubkey,
+    pub consensus: Keypair,
+}
+
+impl Vault {
+    pub async fn start() -> Self {
+        let program_id = Pubkey::new_unique();
+        let mut program_test =
+            ProgramTest::new("smart_vault", program_id, processor!(process_instruction));
+        program_test.prefer_bpf(false);
+
+        let mut context = program_test.start_with_context().await;
+
+        let mint = Keypair::new();
+        let treasury_ata = Keypair::new();
+        let (treasury, _) = Pubkey::find_program_address(
+            &[smart_vault::processor::TREASURY_STATE.as_bytes()],
+            &program_id,
+        );
+
+        let rent = context.banks_client.get_rent().await.unwrap();
+        let payer = context.payer.pubkey();
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &payer,
+                    None,
+                    0,
+                )
+                .unwrap(),
+                system_instruction::create_account(
+                    &payer,
+                    &treasury_ata.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_account3(
+                    &spl_token::ID,
+                    &treasury_ata.pubkey(),
+                    &mint.pubkey(),
+                    &treasury,
+                )
+                .unwrap(),
+            ],
+            Some(&payer),
+            &[&context.payer, &mint, &treasury_ata],
+            context.last_blockhash,
+        );
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+
+        Self {
+            context,
+            client: VaultClient::new(program_id, treasury_ata.pubkey()),
+            mint: mint.pubkey(),
+            consensus: Keypair::new(),
+        }
+    }
+
+    /// The test payer covers fees, `signers` only sign for their own accounts.
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    pub async fn new_actor(&mut self, tokens: u64) -> Actor {
+        let keypair = Keypair::new();
+        let ata = Keypair::new();
+        let payer = self.context.payer.pubkey();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+
+        self.process(
+            &[
+                system_instruction::transfer(&payer, &keypair.pubkey(), ACTOR_LAMPORTS),
+                system_instruction::create_account(
+                    &payer,
+                    &ata.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_account3(
+                    &spl_token::ID,
+                    &ata.pubkey(),
+                    &self.mint,
+                    &keypair.pubkey(),
+                )
+                .unwrap(),
+                spl_token::instruction::mint_to(
+                    &spl_token::ID,
+                    &self.mint,
+                    &ata.pubkey(),
+                    &payer,
+                    &[],
+                    tokens,
+                )
+                .unwrap(),
+            ],
+            &[&ata],
+        )
+        .await
+        .unwrap();
+
+        Actor {
+            keypair,
+            ata: ata.pubkey(),
+        }
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context.banks_client.get_sysvar().await.unwrap()
+    }
+
+    /// Moves the clock forward. Slots only need to advance so consensus approvals go stale.
+    pub async fn warp(&mut self, seconds: u64) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += seconds as i64;
+        clock.slot += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn warp_to(&mut self, unix_timestamp: u64) {
+        let now = self.clock().await.unix_timestamp as u64;
+        assert!(unix_timestamp >= now, "clock can only move forward");
+        self.warp(unix_timestamp - now).await;
+    }
+
+    pub async fn account<T: VaultAccount>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .expect("account not found");
+        T::unpack_account(&account.data).unwrap()
+    }
+
+    pub async fn token_balance(&mut self, ata: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_packed_account_data::<spl_token::state::Account>(*ata)
+            .await
+            .unwrap()
+            .amount
+    }
+
+    pub async fn user_state(&mut self, user: &Pubkey) -> VaultUserState {
+        let address = self.client.find_user_address(user).0;
+        self.account(&address).await
+    }
+
+    pub async fn subscription(&mut self, subscription: &Pubkey) -> VaultUserSubscriptionState {
+        self.account(subscription).await
+    }
+
+    pub async fn reputation(&mut self, operator: &Pubkey) -> VaultReputationState {
+        let address = self.client.find_reputation_address(operator).0;
+        self.account(&address).await
+    }
+
+    /// Nonce the consensus binds approvals to, zero until the operator's first bid.
+    pub async fn operator_nonce(&mut self, operator: &Pubkey) -> u64 {
+        let address = self.client.find_bidder_address(operator).0;
+        match self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+        {
+            Some(account) => {
+                VaultBidderState::unpack_account(&account.data)
+                    .unwrap()
+                    .nonce
+            }
+            None => 0,
+        }
+    }
+
+    pub async fn approval(
+        &mut self,
+        action: ConsensusAction,
+        subscription: &Pubkey,
+        operator: &Pubkey,
+        value: u64,
+    ) -> (Instruction, u64) {
+        let expiry_slot = self.clock().await.slot + APPROVAL_TTL;
+        let operator_nonce = self.operator_nonce(operator).await;
+        let message = self.client.consensus_message(
+            action,
+            subscription,
+            operator,
+            operator_nonce,
+            value,
+            expiry_slot,
+        );
+
+        (sign_approval(&[&self.consensus], &message), expiry_slot)
+    }
+
+    /// Refreshes the operator's attestation under a consensus approval.
+    pub async fn re_attest(
+        &mut self,
+        operator: &Actor,
+        measurement: [u8; 32],
+    ) -> Result<(), BanksClientError> {
+        let attestation_proof = "operator-proof".to_string();
+        let operator_address = self.client.find_operator_address(&operator.pubkey()).0;
+        let attestation_expiry = self
+            .account::<VaultOperatorState>(&operator_address)
+            .await
+            .attestation_expiry;
+        let expiry_slot = self.clock().await.slot + APPROVAL_TTL;
+        let message = self.client.consensus_message(
+            ConsensusAction::ReAttest,
+            &attestation_commitment(&attestation_proof, &measurement),
+            &operator.pubkey(),
+            0,
+            attestation_expiry,
+            expiry_slot,
+        );
+        let approval = sign_approval(&[&self.consensus], &message);
+        let instruction = self.client.re_attest(
+            &operator.pubkey(),
+            attestation_proof,
+            measurement,
+            expiry_slot,
+        );
+        self.process(&[approval, instruction], &[&operator.keypair])
+            .await
+    }
+
+    pub async fn init(&mut self, admin: &Actor) -> Result<(), BanksClientError> {
+        self.init_with(admin, vec![self.consensus.pubkey()], 1, params())
+            .await
+    }
+
+    pub async fn init_with(
+        &mut self,
+        admin: &Actor,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        params: ProtocolParams,
+    ) -> Result<(), BanksClientError> {
+        let instruction = self.client.init(
+            &admin.pubkey(),
+            signers,
+            threshold,
+            MEASUREMENT,
+            "consensus-proof".to_string(),
+            params,
+        );
+        self.process(&[instruction], &[&admin.keypair]).await
+    }
+
+    pub async fn join(&mut self, operator: &Actor, stake: u64) -> Result<(), BanksClientError> {
+        self.join_with(operator, stake, MEASUREMENT).await
+    }
+
+    pub async fn join_with(
+        &mut self,
+        operator: &Actor,
+        stake: u64,
+        measurement: [u8; 32],
+    ) -> Result<(), BanksClientError> {
+        let instruction = self.client.join(
+            &operator.pubkey(),
+            &operator.ata,
+            "operator-proof".to_string(),
+            measurement,
+            &Pubkey::new_unique(),
+            stake,
+        );
+        self.process(&[instruction], &[&operator.keypair]).await
+    }
+
+    pub async fn add_app(&mut self, creator: &Actor, rent: u64) -> Result<u64, BanksClientError> {
+        let app_counter = self.client.find_app_counter_address().0;
+        let app_id = self
+            .account::<VaultAppCounterState>(&app_counter)
+            .await
+            .counter;
+        let instruction = self.client.add_app(
+            &creator.pubkey(),
+            &creator.ata,
+            app_id,
+            rent,
+            "ipfs-hash".to_string(),
+            default_schedule(),
+        );
+        self.process(&[instruction], &[&creator.keypair]).await?;
+        Ok(app_id)
+    }
+
+    pub async fn topup(&mut self, user: &Actor, amount: u64) -> Result<(), BanksClientError> {
+        let instruction = self.client.topup(&user.pubkey(), &user.ata, amount);
+        self.process(&[instruction], &[&user.keypair]).await
+    }
+
+    /// Returns the new subscription's address.
+    pub async fn start_subscription(
+        &mut self,
+        user: &Actor,
+        app_id: u64,
+        max_rent: u64,
+        auction_mode: AuctionMode,
+    ) -> Result<Pubkey, BanksClientError> {
+        let sub_id = self.user_state(&user.pubkey()).await.count;
+        let instruction = self
+            .client
+            .start_subscription(
+                &user.pubkey(),
+                sub_id,
+                app_id,
+                max_rent,
+                "params-hash".to_string(),
+                0,
+            )
+            .auction_mode(auction_mode)
+            .build();
+        self.process(&[instruction], &[&user.keypair]).await?;
+        Ok(self
+            .client
+            .find_subscription_address(&user.pubkey(), sub_id)
+            .0)
+    }
+
+    pub async fn commit_bid(
+        &mut self,
+        bidder: &Actor,
+        subscription: &Pubkey,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let instruction = self.client.commit_bid(
+            &bidder.pubkey(),
+            &bidder.ata,
+            subscription,
+            bid_commitment(amount, &SALT, &bidder.pubkey()),
+        );
+        self.process(&[instruction], &[&bidder.keypair]).await
+    }
+
+    /// Reveals a committed bid, or takes the offer of a reverse Dutch auction.
+    pub async fn bid(
+        &mut self,
+        bidder: &Actor,
+        subscription: &Pubkey,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let (approval, expiry_slot) = self
+            .approval(ConsensusAction::Bid, subscription, &bidder.pubkey(), amount)
+            .await;
+        let instruction =
+            self.client
+                .bid(&bidder.pubkey(), subscription, amount, SALT, expiry_slot);
+        self.process(&[approval, instruction], &[&bidder.keypair])
+            .await
+    }
+
+    pub async fn claimbid(
+        &mut self,
+        bidder: &Actor,
+        user: &Pubkey,
+        subscription: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub = self.subscription(subscription).await;
+        let sub_bids_address = self.client.find_sub_bids_address(subscription).0;
+        let sub_bids = self.account::<VaultSubBidsState>(&sub_bids_address).await;
+        let (approval, expiry_slot) = self
+            .approval(ConsensusAction::ClaimBid, subscription, &bidder.pubkey(), 0)
+            .await;
+        let instruction =
+            self.client
+                .claimbid(&bidder.pubkey(), user, &sub, &sub_bids, expiry_slot);
+        self.process(&[approval, instruction], &[&bidder.keypair])
+            .await
+    }
+
+    /// Reports the work nonce the subscription currently expects.
+    pub async fn report_work(
+        &mut self,
+        executor: &Actor,
+        user: &Pubkey,
+        subscription: &Pubkey,
+        creator_ata: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub = self.subscription(subscription).await;
+        let (approval, expiry_slot) = self
+            .approval(
+                ConsensusAction::ReportWork,
+                subscription,
+                &executor.pubkey(),
+                sub.nonce,
+            )
+            .await;
+        let instruction = self.client.report_work(
+            &executor.ata,
+            user,
+            &sub,
+            creator_ata,
+            sub.nonce,
+            expiry_slot,
+        );
+        self.process(&[approval, instruction], &[&executor.keypair])
+            .await
+    }
+
+    pub async fn report_fault(
+        &mut self,
+        keeper: &Actor,
+        user: &Pubkey,
+        subscription: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub = self.subscription(subscription).await;
+        let instruction = self
+            .client
+            .report_fault(&keeper.pubkey(), &keeper.ata, user, &sub);
+        self.process(&[instruction], &[&keeper.keypair]).await
+    }
+
+    pub async fn reassign_sub(
+        &mut self,
+        keeper: &Actor,
+        user: &Pubkey,
+        subscription: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub = self.subscription(subscription).await;
+        let sub_bids_address = self.client.find_sub_bids_address(subscription).0;
+        let sub_bids = match self
+            .context
+            .banks_client
+            .get_account(sub_bids_address)
+            .await
+            .unwrap()
+        {
+            Some(account) => Some(VaultSubBidsState::unpack_account(&account.data).unwrap()),
+            None => None,
+        };
+        let instruction =
+            self.client
+                .reassign_sub(&keeper.pubkey(), &keeper.ata, user, &sub, sub_bids.as_ref());
+        self.process(&[instruction], &[&keeper.keypair]).await
+    }
+
+    pub async fn settle_bid(
+        &mut self,
+        bidder: &Actor,
+        user: &Pubkey,
+        subscription: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub = self.subscription(subscription).await;
+        let instruction = self
+            .client
+            .settle_bid(&bidder.pubkey(), &bidder.ata, user, &sub);
+        self.process(&[instruction], &[]).await
+    }
+
+    pub async fn close_sub(
+        &mut self,
+        user: &Actor,
+        subscription: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let sub_id = self.subscription(subscription).await.id;
+        let instruction = self.client.close_sub(&user.pubkey(), sub_id);
+        self.process(&[instruction], &[&user.keypair]).await
+    }
+}
+
+/// Initialized protocol with a registered app, a funded subscriber and a staked operator.
+pub struct Market {
+    pub vault: Vault,
+    pub admin: Actor,
+    pub creator: Actor,
+    pub user: Actor,
+    pub operator: Actor,
+    pub app_id: u64,
+}
+
+impl Market {
+    pub async fn start() -> Self {
+        Self::start_with(params()).await
+    }
+
+    pub async fn start_with(params: ProtocolParams) -> Self {
+        let mut vault = Vault::start().await;
+        let admin = vault.new_actor(0).await;
+        let creator = vault.new_actor(0).await;
+        let user = vault.new_actor(10_000).await;
+        let operator = vault.new_actor(10_000).await;
+
+        let signers = vec![vault.consensus.pubkey()];
+        vault.init_with(&admin, signers, 1, params).await.unwrap();
+        vault.join(&operator, STAKE).await.unwrap();
+        let app_id = vault.add_app(&creator, APP_RENT).await.unwrap();
+        vault.topup(&user, DEPOSIT).await.unwrap();
+
+        Self {
+            vault,
+            admin,
+            creator,
+            user,
+            operator,
+            app_id,
+        }
+    }
+
+    /// Registers another staked operator.
+    pub async fn new_operator(&mut self) -> Actor {
+        let operator = self.vault.new_actor(10_000).await;
+        self.vault.join(&operator, STAKE).await.unwrap();
+        operator
+    }
+
+    pub async fn set_measurement(&mut self, measurement: [u8; 32], approved: bool) {
+        let instruction =
+            self.vault
+                .client
+                .set_measurement(&self.admin.pubkey(), measurement, approved);
+        self.vault
+            .process(&[instruction], &[&self.admin.keypair])
+            .await
+            .unwrap();
+    }
+
+    pub async fn re_attest_consensus(&mut self) {
+        let instruction = self.vault.client.re_attest_consensus(
+            &self.admin.pubkey(),
+            "consensus-proof".to_string(),
+            MEASUREMENT,
+        );
+        self.vault
+            .process(&[instruction], &[&self.admin.keypair])
+            .await
+            .unwrap();
+    }
+
+    /// Staked operator attested with [`OPERATOR_MEASUREMENT`].
+    pub async fn revocable_operator(&mut self) -> Actor {
+        self.set_measurement(OPERATOR_MEASUREMENT, true).await;
+        let operator = self.vault.new_actor(10_000).await;
+        self.vault
+            .join_with(&operator, STAKE, OPERATOR_MEASUREMENT)
+            .await
+            .unwrap();
+        operator
+    }
+
+    pub async fn sealed_subscription(&mut self) -> Pubkey {
+        self.vault
+            .start_subscription(&self.user, self.app_id, MAX_RENT, AuctionMode::SealedBid)
+            .await
+            .unwrap()
+    }
+
+    /// Reverse Dutch subscription taken by the default operator at its starting offer.
+    pub async fn assigned_subscription(&mut self) -> Pubkey {
+        let subscription = self.dutch_subscription().await;
+        self.vault
+            .bid(&self.operator, &subscription, MAX_RENT)
+            .await
+            .unwrap();
+        subscription
+    }
+
+    /// Reverse Dutch subscription whose offer starts at max rent, so the first bid takes it.
+    pub async fn dutch_subscription(&mut self) -> Pubkey {
+        self.vault
+            .start_subscription(
+                &self.user,
+                self.app_id,
+                MAX_RENT,
+                AuctionMode::ReverseDutch {
+                    start_rent: MAX_RENT,
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    pub async fn warp_to_reveal(&mut self, subscription: &Pubkey) {
+        let reveal_time = self.vault.subscription(subscription).await.reveal_time;
+        self.vault.warp_to(reveal_time).await;
+    }
+
+    pub async fn warp_to_bid_end(&mut self, subscription: &Pubkey) {
+        let bid_endtime = self.vault.subscription(subscription).await.bid_endtime;
+        self.vault.warp_to(bid_endtime).await;
+    }
+}
@@ -0,0 +1,922 @@
+//! One failing path per `VaultError` variant. `InvalidReport` has no case, no instruction returns it.
+
+mod common;
+
+use common::*;
+use smart_vault::{
+    client::attestation_commitment,
+    error::VaultError,
+    state::{AppSchedule, AuctionMode, ConsensusAction, ProtocolParams, VaultOperatorState},
+};
+use solana_sdk::{hash::hashv, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+#[tokio::test]
+async fn invalid_pda() {
+    let mut market = Market::start().await;
+
+    // the app counter is at 1 already, so app 5 doesn't match the expected seeds
+    let instruction = market.vault.client.add_app(
+        &market.creator.pubkey(),
+        &market.creator.ata,
+        5,
+        APP_RENT,
+        "ipfs-hash".to_string(),
+        default_schedule(),
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.creator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidPDA);
+}
+
+#[tokio::test]
+async fn inefficient_balance() {
+    let mut market = Market::start().await;
+
+    let result = market
+        .vault
+        .start_subscription(&market.user, market.app_id, DEPOSIT, AuctionMode::SealedBid)
+        .await
+        .map(|_| ());
+
+    assert_vault_error(result, VaultError::InefficientBalance);
+}
+
+#[tokio::test]
+async fn inefficient_balance_on_overflowing_max_rent() {
+    let mut market = Market::start().await;
+
+    let result = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            u64::MAX,
+            AuctionMode::SealedBid,
+        )
+        .await
+        .map(|_| ());
+
+    assert_vault_error(result, VaultError::InefficientBalance);
+}
+
+#[tokio::test]
+async fn inefficient_balance_on_first_escrow_topup() {
+    let mut market = Market::start().await;
+    market.sealed_subscription().await;
+
+    // the subscription would leave the pooled balance for an escrow that can't pay one cycle
+    let instruction =
+        market
+            .vault
+            .client
+            .topup_sub(&market.user.pubkey(), &market.user.ata, 0, MIN_TOPUP);
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.user.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InefficientBalance);
+}
+
+#[tokio::test]
+async fn less_then_minimum_topup_amount() {
+    let mut market = Market::start().await;
+
+    let result = market.vault.topup(&market.user, MIN_TOPUP - 1).await;
+
+    assert_vault_error(result, VaultError::LessThenMinimumTopupAmount);
+}
+
+#[tokio::test]
+async fn bid_time_expired() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.warp_to_bid_end(&subscription).await;
+    let result = market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::BidTimeExpired);
+}
+
+#[tokio::test]
+async fn bid_already_claimed() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+    let operator = market.new_operator().await;
+
+    let result = market.vault.bid(&operator, &subscription, MAX_RENT).await;
+
+    assert_vault_error(result, VaultError::BidAlreadyClaimed);
+}
+
+#[tokio::test]
+async fn un_auth_to_claim_bid() {
+    let mut market = Market::start().await;
+    let outbid = market.new_operator().await;
+    let subscription = market.sealed_subscription().await;
+
+    // a reveal above max rent is accepted but never ranked
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market
+        .vault
+        .commit_bid(&outbid, &subscription, MAX_RENT + 1)
+        .await
+        .unwrap();
+    market.warp_to_reveal(&subscription).await;
+    market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market
+        .vault
+        .bid(&outbid, &subscription, MAX_RENT + 1)
+        .await
+        .unwrap();
+
+    market.warp_to_bid_end(&subscription).await;
+    let result = market
+        .vault
+        .claimbid(&outbid, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::UnAuthToClaimBid);
+}
+
+#[tokio::test]
+async fn bid_claim_expired() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.warp_to_reveal(&subscription).await;
+    market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+
+    let bid_endtime = market.vault.subscription(&subscription).await.bid_endtime;
+    market.vault.warp_to(bid_endtime + CLAIM_WINDOW + 1).await;
+    let result = market
+        .vault
+        .claimbid(&market.operator, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::BidClaimExpired);
+}
+
+#[tokio::test]
+async fn subscription_closed() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .close_sub(&market.user, &subscription)
+        .await
+        .unwrap();
+    let instruction =
+        market
+            .vault
+            .client
+            .topup_sub(&market.user.pubkey(), &market.user.ata, 0, MIN_TOPUP);
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.user.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::SubScriptionClosed);
+}
+
+#[tokio::test]
+async fn reported_early() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+
+    let result = market
+        .vault
+        .report_work(
+            &market.operator,
+            &market.user.pubkey(),
+            &subscription,
+            &market.creator.ata,
+        )
+        .await;
+
+    assert_vault_error(result, VaultError::ReportedEarly);
+}
+
+#[tokio::test]
+async fn restart_phase() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+
+    market.vault.warp(REPORT_INTERVAL + REPORT_GRACE + 1).await;
+    market
+        .vault
+        .report_fault(&market.user, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+    let result = market
+        .vault
+        .report_work(
+            &market.operator,
+            &market.user.pubkey(),
+            &subscription,
+            &market.creator.ata,
+        )
+        .await;
+
+    assert_vault_error(result, VaultError::RestartPhase);
+}
+
+#[tokio::test]
+async fn invalid_consesues() {
+    let mut market = Market::start().await;
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch {
+                start_rent: MAX_RENT,
+            },
+        )
+        .await
+        .unwrap();
+
+    let expiry_slot = market.vault.clock().await.slot + APPROVAL_TTL;
+    let message = market.vault.client.consensus_message(
+        ConsensusAction::Bid,
+        &subscription,
+        &market.operator.pubkey(),
+        0,
+        MAX_RENT,
+        expiry_slot,
+    );
+    let approval = sign_approval(&[&Keypair::new()], &message);
+    let instruction = market.vault.client.bid(
+        &market.operator.pubkey(),
+        &subscription,
+        MAX_RENT,
+        SALT,
+        expiry_slot,
+    );
+    let result = market
+        .vault
+        .process(&[approval, instruction], &[&market.operator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidConsesues);
+}
+
+#[tokio::test]
+async fn sig_verification_failed() {
+    let mut market = Market::start().await;
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch {
+                start_rent: MAX_RENT,
+            },
+        )
+        .await
+        .unwrap();
+
+    let expiry_slot = market.vault.clock().await.slot + APPROVAL_TTL;
+    let instruction = market.vault.client.bid(
+        &market.operator.pubkey(),
+        &subscription,
+        MAX_RENT,
+        SALT,
+        expiry_slot,
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.operator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::SigVerificationFailed);
+}
+
+#[tokio::test]
+async fn less_then_minimum_stake_amount() {
+    let mut market = Market::start().await;
+    let operator = market.vault.new_actor(STAKE).await;
+
+    let result = market.vault.join(&operator, 0).await;
+
+    assert_vault_error(result, VaultError::LessThenMinimumStakeAmount);
+}
+
+#[tokio::test]
+async fn operator_not_staked() {
+    let mut market = Market::start_with(ProtocolParams {
+        slash_bps: 10_000,
+        ..params()
+    })
+    .await;
+    let subscription = market.assigned_subscription().await;
+
+    // a full slash leaves the executor without any stake to bid with
+    market.vault.warp(REPORT_INTERVAL + REPORT_GRACE + 1).await;
+    market
+        .vault
+        .report_fault(&market.user, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let subscription = market.sealed_subscription().await;
+    let result = market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::OperatorNotStaked);
+}
+
+#[tokio::test]
+async fn invalid_basis_points() {
+    let mut market = Market::start().await;
+
+    let instruction = market.vault.client.update_config(
+        &market.admin.pubkey(),
+        &market.admin.pubkey(),
+        ProtocolParams {
+            protocol_fee_bps: 10_001,
+            ..params()
+        },
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.admin.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidBasisPoints);
+}
+
+#[tokio::test]
+async fn executor_within_sla() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+
+    let result = market
+        .vault
+        .report_fault(&market.user, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::ExecutorWithinSla);
+}
+
+#[tokio::test]
+async fn not_in_restart_phase() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+
+    let result = market
+        .vault
+        .reassign_sub(&market.user, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::NotInRestartPhase);
+}
+
+#[tokio::test]
+async fn input_too_long() {
+    let mut market = Market::start().await;
+
+    let instruction = market.vault.client.add_app(
+        &market.creator.pubkey(),
+        &market.creator.ata,
+        1,
+        APP_RENT,
+        "x".repeat(MAX_INPUT_LEN as usize + 1),
+        default_schedule(),
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.creator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InputTooLong);
+}
+
+#[tokio::test]
+async fn un_auth_admin() {
+    let mut market = Market::start().await;
+
+    let instruction =
+        market
+            .vault
+            .client
+            .update_config(&market.user.pubkey(), &market.user.pubkey(), params());
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.user.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::UnAuthAdmin);
+}
+
+#[tokio::test]
+async fn rent_below_app_floor() {
+    let mut market = Market::start().await;
+
+    let result = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            APP_RENT - 1,
+            AuctionMode::SealedBid,
+        )
+        .await
+        .map(|_| ());
+
+    assert_vault_error(result, VaultError::RentBelowAppFloor);
+}
+
+#[tokio::test]
+async fn insufficient_accrued_fees() {
+    let mut market = Market::start().await;
+
+    let instruction = market
+        .vault
+        .client
+        .sweep_fees(&market.admin.pubkey(), &market.admin.ata, 1);
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.admin.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InsufficientAccruedFees);
+}
+
+#[tokio::test]
+async fn invalid_key_rotation() {
+    let mut market = Market::start().await;
+
+    let mut clock = market.vault.clock().await;
+    clock.epoch = 3;
+    market.vault.context.set_sysvar(&clock);
+
+    let next_signers = vec![Pubkey::new_unique()];
+    let next_threshold = 1;
+    let activation_epoch = 2;
+    let expiry_slot = clock.slot + APPROVAL_TTL;
+    let next_set_hash = hashv(&[&borsh::to_vec(&(&next_signers, next_threshold)).unwrap()]);
+    let message = market.vault.client.consensus_message(
+        ConsensusAction::RotateConsensus,
+        &Pubkey::new_from_array(next_set_hash.to_bytes()),
+        &Pubkey::default(),
+        0,
+        activation_epoch,
+        expiry_slot,
+    );
+    let approval = sign_approval(&[&market.vault.consensus], &message);
+    let instruction = market.vault.client.rotate_consensus(
+        next_signers,
+        next_threshold,
+        activation_epoch,
+        expiry_slot,
+    );
+    let result = market.vault.process(&[approval, instruction], &[]).await;
+
+    assert_vault_error(result, VaultError::InvalidKeyRotation);
+}
+
+#[tokio::test]
+async fn invalid_signer_set() {
+    let mut vault = Vault::start().await;
+    let admin = vault.new_actor(0).await;
+
+    let signers = vec![vault.consensus.pubkey()];
+    let result = vault.init_with(&admin, signers, 2, params()).await;
+
+    assert_vault_error(result, VaultError::InvalidSignerSet);
+}
+
+#[tokio::test]
+async fn consesues_message_expired() {
+    let mut market = Market::start().await;
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch {
+                start_rent: MAX_RENT,
+            },
+        )
+        .await
+        .unwrap();
+
+    let (approval, expiry_slot) = market
+        .vault
+        .approval(
+            ConsensusAction::Bid,
+            &subscription,
+            &market.operator.pubkey(),
+            MAX_RENT,
+        )
+        .await;
+    market.vault.warp(APPROVAL_TTL + 1).await;
+    let instruction = market.vault.client.bid(
+        &market.operator.pubkey(),
+        &subscription,
+        MAX_RENT,
+        SALT,
+        expiry_slot,
+    );
+    let result = market
+        .vault
+        .process(&[approval, instruction], &[&market.operator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::ConsesuesMessageExpired);
+}
+
+#[tokio::test]
+async fn measurement_not_approved() {
+    let mut market = Market::start().await;
+    let operator = market.vault.new_actor(STAKE).await;
+
+    let instruction = market.vault.client.join(
+        &operator.pubkey(),
+        &operator.ata,
+        "operator-proof".to_string(),
+        [1; 32],
+        &Pubkey::new_unique(),
+        STAKE,
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&operator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::MeasurementNotApproved);
+}
+
+#[tokio::test]
+async fn measurement_registry_full() {
+    let mut market = Market::start().await;
+    let admin = market.admin.pubkey();
+
+    // the consensus measurement already takes one of the 16 slots
+    let instructions: Vec<_> = (1..16)
+        .map(|i| {
+            market
+                .vault
+                .client
+                .set_measurement(&admin, [100 + i; 32], true)
+        })
+        .collect();
+    market
+        .vault
+        .process(&instructions, &[&market.admin.keypair])
+        .await
+        .unwrap();
+
+    let instruction = market.vault.client.set_measurement(&admin, [200; 32], true);
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.admin.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::MeasurementRegistryFull);
+}
+
+#[tokio::test]
+async fn attestation_expired() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market.vault.warp(ATTESTATION_TTL).await;
+    let result = market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::AttestationExpired);
+}
+
+#[tokio::test]
+async fn re_attest_without_consensus_approval() {
+    let mut market = Market::start().await;
+
+    market.vault.warp(ATTESTATION_TTL).await;
+    market.re_attest_consensus().await;
+
+    // the operator approving its own refresh doesn't count towards the consensus threshold
+    let operator_address = market
+        .vault
+        .client
+        .find_operator_address(&market.operator.pubkey())
+        .0;
+    let attestation_expiry = market
+        .vault
+        .account::<VaultOperatorState>(&operator_address)
+        .await
+        .attestation_expiry;
+    let expiry_slot = market.vault.clock().await.slot + APPROVAL_TTL;
+    let message = market.vault.client.consensus_message(
+        ConsensusAction::ReAttest,
+        &attestation_commitment("operator-proof", &MEASUREMENT),
+        &market.operator.pubkey(),
+        0,
+        attestation_expiry,
+        expiry_slot,
+    );
+    let approval = sign_approval(&[&market.operator.keypair], &message);
+    let instruction = market.vault.client.re_attest(
+        &market.operator.pubkey(),
+        "operator-proof".to_string(),
+        MEASUREMENT,
+        expiry_slot,
+    );
+    let result = market
+        .vault
+        .process(&[approval, instruction], &[&market.operator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidConsesues);
+}
+
+#[tokio::test]
+async fn invalid_schedule() {
+    let mut market = Market::start().await;
+
+    let instruction = market.vault.client.add_app(
+        &market.creator.pubkey(),
+        &market.creator.ata,
+        1,
+        APP_RENT,
+        "ipfs-hash".to_string(),
+        AppSchedule {
+            bid_window: 30,
+            reveal_window: 30,
+            ..default_schedule()
+        },
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.creator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidSchedule);
+}
+
+#[tokio::test]
+async fn invalid_schedule_above_protocol_maximum() {
+    let mut market = Market::start().await;
+
+    // an interval near u64::MAX would wrap the report deadline
+    let instruction = market.vault.client.add_app(
+        &market.creator.pubkey(),
+        &market.creator.ata,
+        1,
+        APP_RENT,
+        "ipfs-hash".to_string(),
+        AppSchedule {
+            report_interval: u64::MAX - 1,
+            ..default_schedule()
+        },
+    );
+    let result = market
+        .vault
+        .process(&[instruction], &[&market.creator.keypair])
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidSchedule);
+}
+
+#[tokio::test]
+async fn commit_phase_over() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market.warp_to_reveal(&subscription).await;
+    let result = market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::CommitPhaseOver);
+}
+
+#[tokio::test]
+async fn not_in_reveal_phase() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    let result = market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::NotInRevealPhase);
+}
+
+#[tokio::test]
+async fn invalid_bid_reveal() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.warp_to_reveal(&subscription).await;
+    let result = market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT - 1)
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidBidReveal);
+}
+
+#[tokio::test]
+async fn auction_not_settled() {
+    let mut market = Market::start().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    let result = market
+        .vault
+        .settle_bid(&market.operator, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::AuctionNotSettled);
+}
+
+#[tokio::test]
+async fn claim_window_not_open() {
+    let mut market = Market::start().await;
+    let runner_up = market.new_operator().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, 50)
+        .await
+        .unwrap();
+    market
+        .vault
+        .commit_bid(&runner_up, &subscription, 70)
+        .await
+        .unwrap();
+    market.warp_to_reveal(&subscription).await;
+    market
+        .vault
+        .bid(&market.operator, &subscription, 50)
+        .await
+        .unwrap();
+    market
+        .vault
+        .bid(&runner_up, &subscription, 70)
+        .await
+        .unwrap();
+
+    market.warp_to_bid_end(&subscription).await;
+    let result = market
+        .vault
+        .claimbid(&runner_up, &market.user.pubkey(), &subscription)
+        .await;
+
+    assert_vault_error(result, VaultError::ClaimWindowNotOpen);
+}
+
+#[tokio::test]
+async fn invalid_auction_mode() {
+    let mut market = Market::start().await;
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch { start_rent: 40 },
+        )
+        .await
+        .unwrap();
+
+    let result = market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::InvalidAuctionMode);
+}
+
+#[tokio::test]
+async fn invalid_start_rent() {
+    let mut market = Market::start().await;
+
+    let result = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch {
+                start_rent: MAX_RENT + 1,
+            },
+        )
+        .await
+        .map(|_| ());
+
+    assert_vault_error(result, VaultError::InvalidStartRent);
+}
+
+#[tokio::test]
+async fn bid_above_offer() {
+    let mut market = Market::start().await;
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch { start_rent: 40 },
+        )
+        .await
+        .unwrap();
+
+    let result = market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::BidAboveOffer);
+}
+
+#[tokio::test]
+async fn bid_time_expired_on_dutch_offer() {
+    let mut market = Market::start().await;
+    let subscription = market.dutch_subscription().await;
+
+    market.warp_to_bid_end(&subscription).await;
+    let result = market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await;
+
+    assert_vault_error(result, VaultError::BidTimeExpired);
+}
+
+#[tokio::test]
+async fn restart_phase_on_dutch_offer() {
+    let mut market = Market::start().await;
+    let operator = market.new_operator().await;
+    let subscription = market.dutch_subscription().await;
+
+    market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.vault.warp(REPORT_INTERVAL + REPORT_GRACE + 1).await;
+    market
+        .vault
+        .report_fault(&market.user, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+    let result = market.vault.bid(&operator, &subscription, MAX_RENT).await;
+
+    assert_vault_error(result, VaultError::RestartPhase);
+}
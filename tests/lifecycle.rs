@@ -0,0 +1,528 @@
+mod common;
+
+use common::*;
+use smart_vault::state::{
+    AuctionMode, ConsensusAction, VaultOperatorState, VaultSubBidsState, VaultTreasuryState,
+};
+
+#[tokio::test]
+async fn sealed_bid_subscription_lifecycle() {
+    let mut vault = Vault::start().await;
+    let admin = vault.new_actor(0).await;
+    let creator = vault.new_actor(0).await;
+    let user = vault.new_actor(DEPOSIT).await;
+    let operator = vault.new_actor(2 * STAKE).await;
+
+    vault.init(&admin).await.unwrap();
+    vault.join(&operator, STAKE).await.unwrap();
+    let app_id = vault.add_app(&creator, APP_RENT).await.unwrap();
+    vault.topup(&user, DEPOSIT).await.unwrap();
+
+    let user_state = vault.user_state(&user.pubkey()).await;
+    assert_eq!(user_state.balance, DEPOSIT);
+    assert_eq!(vault.token_balance(&user.ata).await, 0);
+
+    let subscription = vault
+        .start_subscription(&user, app_id, MAX_RENT, AuctionMode::SealedBid)
+        .await
+        .unwrap();
+    let sub = vault.subscription(&subscription).await;
+    let start = vault.clock().await.unix_timestamp as u64;
+    assert_eq!(sub.bid_endtime, start + BID_WINDOW);
+    assert_eq!(sub.reveal_time, start + BID_WINDOW - REVEAL_WINDOW);
+    assert_eq!(
+        vault.user_state(&user.pubkey()).await.reserved,
+        MAX_RENT + APP_RENT
+    );
+
+    let rent = 60;
+    vault
+        .commit_bid(&operator, &subscription, rent)
+        .await
+        .unwrap();
+    vault.warp_to(sub.reveal_time).await;
+    vault.bid(&operator, &subscription, rent).await.unwrap();
+
+    let sub_bids_address = vault.client.find_sub_bids_address(&subscription).0;
+    let sub_bids = vault.account::<VaultSubBidsState>(&sub_bids_address).await;
+    assert_eq!(sub_bids.bids.len(), 1);
+    assert_eq!(sub_bids.bids[0].bidder, operator.pubkey());
+    assert_eq!(sub_bids.bids[0].amount, rent);
+
+    vault.warp_to(sub.bid_endtime).await;
+    vault
+        .claimbid(&operator, &user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let sub = vault.subscription(&subscription).await;
+    assert!(sub.is_assigned);
+    assert_eq!(sub.executor, operator.pubkey());
+    assert_eq!(sub.rent, rent);
+
+    // the bond of the bidder that took the job goes back once the auction is over
+    vault
+        .settle_bid(&operator, &user.pubkey(), &subscription)
+        .await
+        .unwrap();
+    assert_eq!(vault.token_balance(&operator.ata).await, STAKE);
+
+    let cycles = 3;
+    for _ in 0..cycles {
+        vault.warp(REPORT_INTERVAL).await;
+        vault
+            .report_work(&operator, &user.pubkey(), &subscription, &creator.ata)
+            .await
+            .unwrap();
+    }
+
+    let protocol_fee = rent * PROTOCOL_FEE_BPS as u64 / 10_000;
+    let sub = vault.subscription(&subscription).await;
+    assert_eq!(sub.nonce, cycles);
+    assert!(!sub.restart);
+    assert_eq!(
+        vault.token_balance(&operator.ata).await,
+        STAKE + cycles * (rent - protocol_fee)
+    );
+    assert_eq!(vault.token_balance(&creator.ata).await, cycles * APP_RENT);
+    assert_eq!(
+        vault.user_state(&user.pubkey()).await.balance,
+        DEPOSIT - cycles * (rent + APP_RENT)
+    );
+
+    let treasury_address = vault.client.find_treasury_address().0;
+    let treasury = vault.account::<VaultTreasuryState>(&treasury_address).await;
+    assert_eq!(treasury.accrued_fees, cycles * protocol_fee);
+
+    vault.close_sub(&user, &subscription).await.unwrap();
+    assert!(vault.subscription(&subscription).await.closed);
+
+    let user_state = vault.user_state(&user.pubkey()).await;
+    assert_eq!(user_state.reserved, 0);
+
+    // nothing is locked any more, so the whole remaining balance can be withdrawn
+    let withdraw = vault
+        .client
+        .withdraw(&user.pubkey(), &user.ata, user_state.balance);
+    vault.process(&[withdraw], &[&user.keypair]).await.unwrap();
+    assert_eq!(vault.token_balance(&user.ata).await, user_state.balance);
+
+    let sweep = vault
+        .client
+        .sweep_fees(&admin.pubkey(), &admin.ata, treasury.accrued_fees);
+    vault.process(&[sweep], &[&admin.keypair]).await.unwrap();
+    assert_eq!(vault.token_balance(&admin.ata).await, treasury.accrued_fees);
+}
+
+#[tokio::test]
+async fn reverse_dutch_subscription_lifecycle() {
+    let mut market = Market::start().await;
+    let start_rent = 40;
+
+    let subscription = market
+        .vault
+        .start_subscription(
+            &market.user,
+            market.app_id,
+            MAX_RENT,
+            AuctionMode::ReverseDutch { start_rent },
+        )
+        .await
+        .unwrap();
+
+    // halfway through the bid window the offer is halfway between start and max rent
+    market.vault.warp(BID_WINDOW / 2).await;
+    let offer = start_rent + (MAX_RENT - start_rent) / 2;
+    market
+        .vault
+        .bid(&market.operator, &subscription, offer)
+        .await
+        .unwrap();
+
+    let sub = market.vault.subscription(&subscription).await;
+    assert!(sub.is_assigned);
+    assert_eq!(sub.executor, market.operator.pubkey());
+    assert_eq!(sub.rent, offer);
+
+    for _ in 0..2 {
+        market.vault.warp(REPORT_INTERVAL).await;
+        market
+            .vault
+            .report_work(
+                &market.operator,
+                &market.user.pubkey(),
+                &subscription,
+                &market.creator.ata,
+            )
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(market.vault.subscription(&subscription).await.nonce, 2);
+    assert_eq!(
+        market.vault.token_balance(&market.creator.ata).await,
+        2 * APP_RENT
+    );
+
+    market
+        .vault
+        .close_sub(&market.user, &subscription)
+        .await
+        .unwrap();
+    assert!(market.vault.subscription(&subscription).await.closed);
+}
+
+#[tokio::test]
+async fn runner_up_claims_after_winner_misses_its_window() {
+    let mut market = Market::start().await;
+    let runner_up = market.new_operator().await;
+    let subscription = market.sealed_subscription().await;
+
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, 50)
+        .await
+        .unwrap();
+    market
+        .vault
+        .commit_bid(&runner_up, &subscription, 70)
+        .await
+        .unwrap();
+
+    market.warp_to_reveal(&subscription).await;
+    market
+        .vault
+        .bid(&market.operator, &subscription, 50)
+        .await
+        .unwrap();
+    market
+        .vault
+        .bid(&runner_up, &subscription, 70)
+        .await
+        .unwrap();
+
+    let reputation = market.vault.reputation(&market.operator.pubkey()).await;
+    let bid_endtime = market.vault.subscription(&subscription).await.bid_endtime;
+    market.vault.warp_to(bid_endtime + CLAIM_WINDOW + 1).await;
+    market
+        .vault
+        .claimbid(&runner_up, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let sub = market.vault.subscription(&subscription).await;
+    assert_eq!(sub.executor, runner_up.pubkey());
+    assert_eq!(sub.rent, 70);
+    assert_eq!(sub.winner_rank, 1);
+
+    // the skipped winner's bond went to the subscriber
+    assert_eq!(
+        market.vault.user_state(&market.user.pubkey()).await.balance,
+        DEPOSIT + BID_BOND
+    );
+    let skipped = market.vault.reputation(&market.operator.pubkey()).await;
+    assert!(skipped.score < reputation.score);
+    assert_eq!(skipped.faults, reputation.faults + 1);
+
+    market.vault.warp(REPORT_INTERVAL).await;
+    market
+        .vault
+        .report_work(
+            &runner_up,
+            &market.user.pubkey(),
+            &subscription,
+            &market.creator.ata,
+        )
+        .await
+        .unwrap();
+    assert_eq!(market.vault.subscription(&subscription).await.nonce, 1);
+}
+
+#[tokio::test]
+async fn every_ranked_bidder_is_penalized_once_all_claim_windows_lapse() {
+    let mut market = Market::start().await;
+    let keeper = market.vault.new_actor(0).await;
+    let runner_up = market.new_operator().await;
+    let subscription = market.sealed_subscription().await;
+
+    for (bidder, amount) in [(&market.operator, 50), (&runner_up, 70)] {
+        market
+            .vault
+            .commit_bid(bidder, &subscription, amount)
+            .await
+            .unwrap();
+    }
+    market.warp_to_reveal(&subscription).await;
+    for (bidder, amount) in [(&market.operator, 50), (&runner_up, 70)] {
+        market
+            .vault
+            .bid(bidder, &subscription, amount)
+            .await
+            .unwrap();
+    }
+
+    let bidders = [market.operator.pubkey(), runner_up.pubkey()];
+    let mut reputations = Vec::new();
+    for bidder in &bidders {
+        reputations.push(market.vault.reputation(bidder).await);
+    }
+
+    let bid_endtime = market.vault.subscription(&subscription).await.bid_endtime;
+    market
+        .vault
+        .warp_to(bid_endtime + 2 * CLAIM_WINDOW + 1)
+        .await;
+    market
+        .vault
+        .reassign_sub(&keeper, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        market.vault.subscription(&subscription).await.auction_round,
+        1
+    );
+    assert_eq!(
+        market.vault.user_state(&market.user.pubkey()).await.balance,
+        DEPOSIT + 2 * BID_BOND
+    );
+    for (bidder, reputation) in bidders.iter().zip(reputations) {
+        let penalized = market.vault.reputation(bidder).await;
+        assert!(penalized.score < reputation.score);
+        assert_eq!(penalized.faults, reputation.faults + 1);
+    }
+}
+
+#[tokio::test]
+async fn sealed_auction_without_reveals_is_reopened() {
+    let mut market = Market::start().await;
+    let keeper = market.vault.new_actor(0).await;
+    let subscription = market.sealed_subscription().await;
+
+    market.warp_to_bid_end(&subscription).await;
+    market.vault.warp(1).await;
+    market
+        .vault
+        .reassign_sub(&keeper, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let sub = market.vault.subscription(&subscription).await;
+    let now = market.vault.clock().await.unix_timestamp as u64;
+    assert!(!sub.is_assigned);
+    assert_eq!(sub.auction_round, 1);
+    assert_eq!(sub.bid_endtime, now + BID_WINDOW);
+    assert_eq!(market.vault.token_balance(&keeper.ata).await, 0);
+
+    // bidders of the new round can commit right away
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, 50)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn faulty_executor_is_slashed_and_subscription_reauctioned() {
+    let mut market = Market::start().await;
+    let keeper = market.vault.new_actor(0).await;
+    let subscription = market.assigned_subscription().await;
+
+    market.vault.warp(REPORT_INTERVAL + REPORT_GRACE + 1).await;
+    market
+        .vault
+        .report_fault(&keeper, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let slashed = STAKE * SLASH_BPS as u64 / 10_000;
+    let reporter_amount = slashed * REPORTER_SHARE_BPS as u64 / 10_000;
+    assert!(market.vault.subscription(&subscription).await.restart);
+    assert_eq!(
+        market.vault.token_balance(&keeper.ata).await,
+        reporter_amount
+    );
+    assert_eq!(
+        market.vault.user_state(&market.user.pubkey()).await.balance,
+        DEPOSIT + slashed - reporter_amount
+    );
+
+    market
+        .vault
+        .reassign_sub(&keeper, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let sub = market.vault.subscription(&subscription).await;
+    assert!(!sub.is_assigned);
+    assert!(!sub.restart);
+    assert_eq!(sub.auction_round, 1);
+    assert_eq!(
+        market.vault.token_balance(&keeper.ata).await,
+        reporter_amount + KEEPER_BOUNTY
+    );
+
+    // a new round opens right away and the offer starts over
+    market
+        .vault
+        .bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    assert!(market.vault.subscription(&subscription).await.is_assigned);
+}
+
+#[tokio::test]
+async fn report_with_wrong_nonce_slashes_executor() {
+    let mut market = Market::start().await;
+    let subscription = market.assigned_subscription().await;
+    let reputation = market.vault.reputation(&market.operator.pubkey()).await;
+
+    market.vault.warp(REPORT_INTERVAL).await;
+    let sub = market.vault.subscription(&subscription).await;
+    let wrong_nonce = sub.nonce + 1;
+    let (approval, expiry_slot) = market
+        .vault
+        .approval(
+            ConsensusAction::ReportWork,
+            &subscription,
+            &market.operator.pubkey(),
+            wrong_nonce,
+        )
+        .await;
+    let instruction = market.vault.client.report_work(
+        &market.operator.ata,
+        &market.user.pubkey(),
+        &sub,
+        &market.creator.ata,
+        wrong_nonce,
+        expiry_slot,
+    );
+    market
+        .vault
+        .process(&[approval, instruction], &[&market.operator.keypair])
+        .await
+        .unwrap();
+
+    let slashed = STAKE * SLASH_BPS as u64 / 10_000;
+    let operator_address = market
+        .vault
+        .client
+        .find_operator_address(&market.operator.pubkey())
+        .0;
+    let operator_state = market
+        .vault
+        .account::<VaultOperatorState>(&operator_address)
+        .await;
+    let sub = market.vault.subscription(&subscription).await;
+    assert!(sub.restart);
+    assert_eq!(sub.nonce, 0);
+    assert_eq!(operator_state.stake, STAKE - slashed);
+    assert_eq!(
+        market.vault.user_state(&market.user.pubkey()).await.balance,
+        DEPOSIT + slashed
+    );
+
+    let after = market.vault.reputation(&market.operator.pubkey()).await;
+    assert!(after.score < reputation.score);
+    assert_eq!(after.faults, reputation.faults + 1);
+}
+
+#[tokio::test]
+async fn executor_with_revoked_measurement_restarts_on_report() {
+    let mut market = Market::start().await;
+    let operator = market.revocable_operator().await;
+    let subscription = market.dutch_subscription().await;
+
+    market
+        .vault
+        .bid(&operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.set_measurement(OPERATOR_MEASUREMENT, false).await;
+
+    market.vault.warp(REPORT_INTERVAL).await;
+    market
+        .vault
+        .report_work(
+            &operator,
+            &market.user.pubkey(),
+            &subscription,
+            &market.creator.ata,
+        )
+        .await
+        .unwrap();
+
+    let slashed = STAKE * SLASH_BPS as u64 / 10_000;
+    let operator_address = market
+        .vault
+        .client
+        .find_operator_address(&operator.pubkey())
+        .0;
+    let operator_state = market
+        .vault
+        .account::<VaultOperatorState>(&operator_address)
+        .await;
+    assert!(market.vault.subscription(&subscription).await.restart);
+    assert_eq!(operator_state.stake, STAKE - slashed);
+}
+
+#[tokio::test]
+async fn executor_with_revoked_measurement_is_reported_right_away() {
+    let mut market = Market::start().await;
+    let keeper = market.vault.new_actor(0).await;
+    let operator = market.revocable_operator().await;
+    let subscription = market.dutch_subscription().await;
+
+    market
+        .vault
+        .bid(&operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+    market.set_measurement(OPERATOR_MEASUREMENT, false).await;
+
+    // still well within the report window
+    market
+        .vault
+        .report_fault(&keeper, &market.user.pubkey(), &subscription)
+        .await
+        .unwrap();
+
+    let slashed = STAKE * SLASH_BPS as u64 / 10_000;
+    assert!(market.vault.subscription(&subscription).await.restart);
+    assert_eq!(
+        market.vault.token_balance(&keeper.ata).await,
+        slashed * REPORTER_SHARE_BPS as u64 / 10_000
+    );
+}
+
+#[tokio::test]
+async fn lapsed_operator_re_attests_under_consensus_approval() {
+    let mut market = Market::start().await;
+
+    market.vault.warp(ATTESTATION_TTL).await;
+    market.re_attest_consensus().await;
+    market
+        .vault
+        .re_attest(&market.operator, MEASUREMENT)
+        .await
+        .unwrap();
+
+    let now = market.vault.clock().await.unix_timestamp as u64;
+    let operator_address = market
+        .vault
+        .client
+        .find_operator_address(&market.operator.pubkey())
+        .0;
+    let operator_state = market
+        .vault
+        .account::<VaultOperatorState>(&operator_address)
+        .await;
+    assert_eq!(operator_state.attestation_expiry, now + ATTESTATION_TTL);
+
+    let subscription = market.sealed_subscription().await;
+    market
+        .vault
+        .commit_bid(&market.operator, &subscription, MAX_RENT)
+        .await
+        .unwrap();
+}